@@ -0,0 +1,585 @@
+//! `where-is`: find files by name, glob or regular expression.
+
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+use where_is::{
+    CaseFold, DirEntry, EntryType, ErrorPolicy, Finder, MatchOn, Normalization, Pattern, SortKey,
+};
+
+const USAGE: &str = "\
+Usage: where-is [OPTIONS] PATTERN [ROOT...]
+
+Searches each ROOT (by default, the current directory) for entries whose
+names match PATTERN, printing one path per line. Exits with status 0 if
+anything is found, even if the reader of the output exits early, 1 if
+nothing is found, or 2 if the arguments are invalid or the results cannot
+be written. Short options may be bundled, as in -gi or -n5.
+
+Pattern options:
+  -g, --glob             Treat PATTERN as a shell glob
+  -r, --regex            Treat PATTERN as a regular expression
+  -p, --full-path        Match against the path relative to ROOT, rather
+                         than the file name
+  -i, --ignore-case      Ignore case and Unicode normalization differences
+
+Traversal options:
+      --min-depth N      Only show entries at least N levels below ROOT
+      --max-depth N      Only show entries at most N levels below ROOT
+  -t, --type TYPE        Only show entries of TYPE: f (file), d (directory),
+                         l (symbolic link), s (socket), p (FIFO),
+                         b (block device) or c (character device)
+  -L, --follow           Follow symbolic links
+      --ignore-files     Skip entries excluded by .gitignore, .ignore or
+                         .whereisignore files and git's exclude files
+  -B, --breadth-first    Search breadth-first, showing shallower entries
+                         before deeper ones
+      --shallowest       Only show matches at the shallowest depth with any
+                         match; implies --breadth-first
+  -n, --max-results N    Stop after showing N matches
+      --max-per-dir N    Show at most N matches from each directory,
+                         skipping the rest of the directory
+  -j, --threads N        Search with N threads, or one per CPU if N is 0,
+                         printing results in no particular order
+
+Output options:
+  -s, --sort KEY         Print results once the search completes, sorted by
+                         KEY: name, natural (name, with numbers compared
+                         by value), depth, size or mtime
+      --format FORMAT    Print results as FORMAT: plain (the default),
+                         null (NUL-terminated) or json (one object per line)
+  -0, --print0           Same as --format null
+  -q, --quiet            Print nothing; only set the exit status
+  -h, --help             Print this message
+  -V, --version          Print the version
+";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Syntax {
+    Exact,
+    Glob,
+    Regex,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Plain,
+    Null,
+    Json,
+    Quiet,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Args {
+    pattern: OsString,
+    roots: Vec<PathBuf>,
+    syntax: Syntax,
+    full_path: bool,
+    ignore_case: bool,
+    min_depth: Option<usize>,
+    max_depth: Option<usize>,
+    types: Vec<EntryType>,
+    follow_links: bool,
+    ignore_files: bool,
+    breadth_first: bool,
+    shallowest: bool,
+    max_results: Option<usize>,
+    max_per_dir: Option<usize>,
+    threads: Option<usize>,
+    sort: Option<SortKey>,
+    format: Format,
+}
+
+/// The result of parsing the command line.
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Search(Args),
+    Help,
+    Version,
+}
+
+/// The short flags which take a value, which ends any bundle they are in.
+const SHORT_FLAGS_WITH_VALUES: &[&str] = &["-t", "-n", "-j", "-s"];
+
+fn parse_args<I: IntoIterator<Item = OsString>>(args: I) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let mut positional: Vec<OsString> = Vec::new();
+    let mut syntax = Syntax::Exact;
+    let mut full_path = false;
+    let mut ignore_case = false;
+    let mut min_depth = None;
+    let mut max_depth = None;
+    let mut types = Vec::new();
+    let mut follow_links = false;
+    let mut ignore_files = false;
+    let mut breadth_first = false;
+    let mut shallowest = false;
+    let mut max_results = None;
+    let mut max_per_dir = None;
+    let mut threads = None;
+    let mut sort = None;
+    let mut format = Format::Plain;
+
+    // The rest of a bundle of short flags, such as `-i` after `-g` in `-gi`.
+    let mut bundled: Option<String> = None;
+    while let Some(arg) = bundled.take().map(OsString::from).or_else(|| args.next()) {
+        let arg_str = match arg.to_str() {
+            Some(s) if s.starts_with('-') && s != "-" => s.to_string(),
+            _ => {
+                positional.push(arg);
+                continue;
+            }
+        };
+        if arg_str == "--" {
+            positional.extend(args.by_ref());
+            break;
+        }
+        // Accept both `--flag value` and `--flag=value`, and for short
+        // flags, `-f value` and `-fvalue`.
+        let (flag, inline_value) = match (arg_str.find('='), arg_str.char_indices().nth(2)) {
+            (Some(i), _) if arg_str.starts_with("--") => {
+                (arg_str[..i].to_string(), Some(arg_str[i + 1..].to_string()))
+            }
+            (_, Some((i, _))) if !arg_str.starts_with("--") => {
+                let (flag, rest) = arg_str.split_at(i);
+                if SHORT_FLAGS_WITH_VALUES.contains(&flag) {
+                    (flag.to_string(), Some(rest.to_string()))
+                } else {
+                    bundled = Some(format!("-{}", rest));
+                    (flag.to_string(), None)
+                }
+            }
+            _ => (arg_str, None),
+        };
+        let mut value = |name: &str| -> Result<String, String> {
+            match inline_value.clone() {
+                Some(v) => Ok(v),
+                None => args
+                    .next()
+                    .map(|v| v.to_string_lossy().into_owned())
+                    .ok_or_else(|| format!("{} requires a value", name)),
+            }
+        };
+        match flag.as_str() {
+            "-g" | "--glob" => syntax = Syntax::Glob,
+            "-r" | "--regex" => syntax = Syntax::Regex,
+            "-p" | "--full-path" => full_path = true,
+            "-i" | "--ignore-case" => ignore_case = true,
+            "--min-depth" => min_depth = Some(parse_number(&flag, &value(&flag)?)?),
+            "--max-depth" => max_depth = Some(parse_number(&flag, &value(&flag)?)?),
+            "-t" | "--type" => {
+                let v = value(&flag)?;
+                types.push(match v.as_str() {
+                    "f" | "file" => EntryType::File,
+                    "d" | "dir" | "directory" => EntryType::Dir,
+                    "l" | "symlink" => EntryType::Symlink,
+                    "s" | "socket" => EntryType::Socket,
+                    "p" | "fifo" => EntryType::Fifo,
+                    "b" | "block" => EntryType::BlockDevice,
+                    "c" | "char" => EntryType::CharDevice,
+                    _ => return Err(format!("unknown type '{}'", v)),
+                })
+            }
+            "-L" | "--follow" => follow_links = true,
+            "--ignore-files" => ignore_files = true,
+            "-B" | "--breadth-first" => breadth_first = true,
+            "--shallowest" => shallowest = true,
+            "-n" | "--max-results" => max_results = Some(parse_number(&flag, &value(&flag)?)?),
+            "--max-per-dir" => max_per_dir = Some(parse_number(&flag, &value(&flag)?)?),
+            "-j" | "--threads" => threads = Some(parse_number(&flag, &value(&flag)?)?),
+            "-s" | "--sort" => {
+                let v = value(&flag)?;
+                sort = Some(match v.as_str() {
+                    "name" => SortKey::Name,
+                    "natural" => SortKey::NaturalName,
+                    "depth" => SortKey::Depth,
+                    "size" => SortKey::Size,
+                    "mtime" => SortKey::Modified,
+                    _ => return Err(format!("unknown sort key '{}'", v)),
+                })
+            }
+            "--format" => {
+                let v = value(&flag)?;
+                format = match v.as_str() {
+                    "plain" => Format::Plain,
+                    "null" => Format::Null,
+                    "json" => Format::Json,
+                    _ => return Err(format!("unknown format '{}'", v)),
+                }
+            }
+            "-0" | "--print0" => format = Format::Null,
+            "-q" | "--quiet" => format = Format::Quiet,
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            _ => return Err(format!("unknown option '{}'", flag)),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    // Exact names may be any bytes, but globs and regular expressions
+    // must be valid UTF-8.
+    let pattern = match positional.next() {
+        Some(pattern) if syntax == Syntax::Exact || pattern.to_str().is_some() => pattern,
+        Some(_) => return Err("PATTERN must be valid UTF-8".to_string()),
+        None => return Err("missing PATTERN".to_string()),
+    };
+    let mut roots: Vec<PathBuf> = positional.map(PathBuf::from).collect();
+    if roots.is_empty() {
+        roots.push(PathBuf::from("."));
+    }
+    Ok(Command::Search(Args {
+        pattern,
+        roots,
+        syntax,
+        full_path,
+        ignore_case,
+        min_depth,
+        max_depth,
+        types,
+        follow_links,
+        ignore_files,
+        breadth_first,
+        shallowest,
+        max_results,
+        max_per_dir,
+        threads,
+        sort,
+        format,
+    }))
+}
+
+fn parse_number(flag: &str, value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, got '{}'", flag, value))
+}
+
+impl Args {
+    fn pattern(&self) -> Result<Pattern, where_is::Error> {
+        let pattern = match self.syntax {
+            Syntax::Exact => Pattern::exact(&self.pattern),
+            Syntax::Glob => Pattern::glob(&self.pattern.to_string_lossy())?,
+            Syntax::Regex => Pattern::regex(&self.pattern.to_string_lossy())?,
+        };
+        let pattern = if self.ignore_case {
+            pattern
+                .case_fold(CaseFold::Unicode)
+                .normalize(Normalization::Nfc)
+        } else {
+            pattern
+        };
+        Ok(if self.full_path {
+            pattern.match_on(MatchOn::RelativePath)
+        } else {
+            pattern
+        })
+    }
+}
+
+fn print_entry(out: &mut impl Write, format: Format, entry: &DirEntry) -> io::Result<()> {
+    match format {
+        Format::Plain => {
+            write_path(out, entry)?;
+            out.write_all(b"\n")
+        }
+        Format::Null => {
+            write_path(out, entry)?;
+            out.write_all(b"\0")
+        }
+        Format::Json => {
+            let file_type = entry.file_type();
+            let kind = if file_type.is_dir() {
+                "directory"
+            } else if file_type.is_file() {
+                "file"
+            } else if file_type.is_symlink() {
+                "symlink"
+            } else {
+                "other"
+            };
+            writeln!(
+                out,
+                "{{\"path\":{},\"depth\":{},\"type\":\"{}\"}}",
+                json_string(&entry.path().to_string_lossy()),
+                entry.depth(),
+                kind
+            )
+        }
+        Format::Quiet => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn write_path(out: &mut impl Write, entry: &DirEntry) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    out.write_all(entry.path().as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+fn write_path(out: &mut impl Write, entry: &DirEntry) -> io::Result<()> {
+    write!(out, "{}", entry.path().display())
+}
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Runs the search, writing its results to `out` and returning the exit
+/// status.
+fn run(args: Args, out: &mut impl Write) -> i32 {
+    let pattern = match args.pattern() {
+        Ok(pattern) => pattern,
+        Err(err) => {
+            eprintln!("where-is: {}", err);
+            return 2;
+        }
+    };
+    let mut found = false;
+    let (first, rest) = args.roots.split_first().expect("at least one root");
+    let mut finder = rest
+        .iter()
+        .fold(Finder::with_pattern(first, pattern), Finder::add_root)
+        .follow_links(args.follow_links)
+        .ignore_files(args.ignore_files)
+        .breadth_first(args.breadth_first)
+        .stop_at_shallowest(args.shallowest)
+        .error_policy(ErrorPolicy::Yield);
+    if let Some(depth) = args.min_depth {
+        finder = finder.min_depth(depth);
+    }
+    if let Some(depth) = args.max_depth {
+        finder = finder.max_depth(depth);
+    }
+    if let Some(n) = args.max_results {
+        finder = finder.max_results(n);
+    }
+    if let Some(n) = args.max_per_dir {
+        finder = finder.max_results_per_dir(n);
+    }
+    finder = args.types.iter().copied().fold(finder, Finder::entry_type);
+    if let Some(key) = args.sort {
+        finder = finder.sort_results(key);
+    }
+    let results: Box<dyn Iterator<Item = where_is::Result<DirEntry>>> = match args.threads {
+        Some(n) => Box::new(finder.threads(n).par_try_iter()),
+        None => Box::new(finder.try_iter()),
+    };
+    for result in results {
+        match result {
+            Ok(entry) => {
+                found = true;
+                if args.format == Format::Quiet {
+                    return 0;
+                }
+                if let Err(err) = print_entry(out, args.format, &entry) {
+                    return write_failed(err);
+                }
+            }
+            Err(err) => eprintln!("where-is: {}", err),
+        }
+    }
+    if let Err(err) = out.flush() {
+        return write_failed(err);
+    }
+    if found {
+        0
+    } else {
+        1
+    }
+}
+
+/// Reports a failure to write the results, returning the exit status. Since
+/// something was found, a reader which went away is not a failure.
+fn write_failed(err: io::Error) -> i32 {
+    // As in `where-is foo | head -1`.
+    if err.kind() == io::ErrorKind::BrokenPipe {
+        return 0;
+    }
+    eprintln!("where-is: {}", err);
+    2
+}
+
+fn main() {
+    let status = match parse_args(std::env::args_os().skip(1)) {
+        Ok(Command::Search(args)) => {
+            let stdout = io::stdout();
+            run(args, &mut io::BufWriter::new(stdout.lock()))
+        }
+        Ok(Command::Help) => {
+            print!("{}", USAGE);
+            0
+        }
+        Ok(Command::Version) => {
+            println!("where-is {}", env!("CARGO_PKG_VERSION"));
+            0
+        }
+        Err(message) => {
+            eprintln!("where-is: {}\n\n{}", message, USAGE);
+            2
+        }
+    };
+    process::exit(status);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(OsString::from))
+    }
+
+    fn search(args: &[&str]) -> Args {
+        match parse(args) {
+            Ok(Command::Search(args)) => args,
+            other => panic!("unexpected parse: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn defaults() {
+        let args = search(&["Cargo.toml"]);
+        assert_eq!(args.pattern, "Cargo.toml");
+        assert_eq!(args.roots, vec![PathBuf::from(".")]);
+        assert_eq!(args.syntax, Syntax::Exact);
+        assert_eq!(args.format, Format::Plain);
+    }
+
+    #[test]
+    fn options() {
+        let args = search(&[
+            "-g",
+            "*.rs",
+            "-i",
+            "src",
+            "tests",
+            "--max-depth=3",
+            "--min-depth",
+            "1",
+            "-t",
+            "f",
+            "-L",
+            "--ignore-files",
+            "-B",
+            "--shallowest",
+            "-n",
+            "5",
+            "--max-per-dir=2",
+            "-j",
+            "4",
+            "--sort=natural",
+            "--format",
+            "json",
+        ]);
+        assert_eq!(args.pattern, "*.rs");
+        assert_eq!(
+            args.roots,
+            vec![PathBuf::from("src"), PathBuf::from("tests")]
+        );
+        assert_eq!(args.syntax, Syntax::Glob);
+        assert_eq!(args.min_depth, Some(1));
+        assert_eq!(args.max_depth, Some(3));
+        assert_eq!(args.types, vec![EntryType::File]);
+        assert!(args.follow_links);
+        assert!(args.ignore_case);
+        assert!(args.ignore_files);
+        assert!(args.breadth_first);
+        assert!(args.shallowest);
+        assert_eq!(args.max_results, Some(5));
+        assert_eq!(args.max_per_dir, Some(2));
+        assert_eq!(args.threads, Some(4));
+        assert_eq!(args.sort, Some(SortKey::NaturalName));
+        assert_eq!(args.format, Format::Json);
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--max-depth", "x", "a"]).is_err());
+        assert!(parse(&["--bogus", "a"]).is_err());
+        assert_eq!(search(&["--", "-a"]).pattern, "-a");
+    }
+
+    #[test]
+    fn bundled_flags() {
+        let args = search(&["-gi", "-Ltf", "-n5", "*.rs"]);
+        assert_eq!(args.syntax, Syntax::Glob);
+        assert!(args.ignore_case);
+        assert!(args.follow_links);
+        assert_eq!(args.types, vec![EntryType::File]);
+        assert_eq!(args.max_results, Some(5));
+        assert_eq!(search(&["-Bj", "2", "a"]).threads, Some(2));
+        assert!(parse(&["-gx", "a"]).is_err());
+    }
+
+    /// A writer which fails with `kind` once anything is written to it.
+    struct Failing(io::ErrorKind);
+
+    impl Write for Failing {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(self.0, "write failed"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn exit_status() {
+        let tmp_dir = tempdir::TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path().to_str().unwrap();
+        std::fs::write(tmp_dir.path().join("a.txt"), "").unwrap();
+        let mut out = Vec::new();
+
+        assert_eq!(run(search(&["a.txt", root]), &mut out), 0);
+        assert_eq!(
+            out,
+            format!("{}\n", tmp_dir.path().join("a.txt").display()).into_bytes()
+        );
+        assert_eq!(run(search(&["b.txt", root]), &mut Vec::new()), 1);
+        assert_eq!(run(search(&["-r", "a(", root]), &mut Vec::new()), 2);
+        let full = &mut Failing(io::ErrorKind::Other);
+        assert_eq!(run(search(&["a.txt", root]), full), 2);
+        let closed = &mut Failing(io::ErrorKind::BrokenPipe);
+        assert_eq!(run(search(&["a.txt", root]), closed), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_names() {
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+        let tmp_dir = tempdir::TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path().to_str().unwrap();
+        // "café.txt" in Latin-1, which is not valid UTF-8.
+        let name = std::ffi::OsStr::from_bytes(b"caf\xe9.txt");
+        std::fs::write(tmp_dir.path().join(name), "").unwrap();
+        let mut out = Vec::new();
+
+        assert_eq!(run(search(&["-g", "caf?.txt", root]), &mut out), 0);
+        let mut expected = tmp_dir.path().join(name).into_os_string().into_vec();
+        expected.push(b'\n');
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn json_escaping() {
+        assert_eq!(json_string("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+    }
+}
@@ -0,0 +1,339 @@
+use crate::matcher::io_error;
+use crate::pattern::compile_regex;
+use crate::{CaseFold, DirEntry, Error, Matcher};
+use regex::bytes::{Regex, RegexBuilder};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+/// The longest prefix of a line which is searched.
+const MAX_LINE_LEN: u64 = 8 << 20;
+
+/// Matches regular files whose contents contain a literal string or a
+/// regular expression.
+///
+/// Files are searched a line at a time, so a match cannot span lines.
+/// Only the first 8 MiB of each line are searched, so that files without
+/// line breaks need not be held in memory. Entries other than regular
+/// files never match.
+///
+/// As a [`Matcher`], only whether a file matches is reported. To learn
+/// where each file matched, use [`Finder::search_contents`] or
+/// [`Contents::search`].
+///
+/// ```no_run
+/// use where_is::{Contents, Finder};
+///
+/// let finder = Finder::with_glob(".", "Cargo.toml")?;
+/// for m in finder.search_contents(Contents::literal("[workspace]")) {
+///     for line in m.lines() {
+///         println!("{}:{}", m.entry().path().display(), line.line_number());
+///     }
+/// }
+/// # Ok::<(), where_is::Error>(())
+/// ```
+///
+/// [`Matcher`]: trait.Matcher.html
+/// [`Finder::search_contents`]: struct.Finder.html#method.search_contents
+/// [`Contents::search`]: struct.Contents.html#method.search
+#[derive(Clone, Debug)]
+pub struct Contents {
+    source: String,
+    re: Regex,
+    skip_binary: bool,
+    max_bytes: Option<u64>,
+}
+
+impl Contents {
+    /// Matches files which contain `text`.
+    pub fn literal(text: &str) -> Self {
+        let source = regex::escape(text);
+        let re = Regex::new(&source).expect("escaped text is a valid regex");
+        Self::new(source, re)
+    }
+
+    /// Matches files which contain a match for the regular expression `re`.
+    ///
+    /// `^` and `$` match at the start and end of each line.
+    pub fn regex(re: &str) -> Result<Self, Error> {
+        Ok(Self::new(
+            re.to_string(),
+            Regex::new(re).map_err(|err| Error::invalid_pattern(re, err))?,
+        ))
+    }
+
+    fn new(source: String, re: Regex) -> Self {
+        Contents {
+            source,
+            re,
+            skip_binary: true,
+            max_bytes: None,
+        }
+    }
+
+    /// Sets how letter case is treated. Defaults to
+    /// [`CaseFold::Sensitive`].
+    ///
+    /// Lines are searched as they are stored, so that the offsets of
+    /// matches refer to the file. [`CaseFold::Unicode`] therefore ignores
+    /// case one character at a time, by simple rather than full case
+    /// folding: `Straße` matches `STRAẞE`, but not `STRASSE`.
+    ///
+    /// [`CaseFold::Sensitive`]: enum.CaseFold.html#variant.Sensitive
+    /// [`CaseFold::Unicode`]: enum.CaseFold.html#variant.Unicode
+    pub fn case_fold(mut self, case_fold: CaseFold) -> Self {
+        self.re = match case_fold {
+            CaseFold::Unicode => RegexBuilder::new(&self.source)
+                .case_insensitive(true)
+                .build()
+                .expect("previously valid regex failed to compile"),
+            _ => compile_regex(&self.source, case_fold),
+        };
+        self
+    }
+
+    /// Sets whether binary files, which contain a NUL byte, are skipped.
+    /// Enabled by default.
+    ///
+    /// A file is only known to be binary once a NUL byte is read, so a
+    /// binary file which is searched no further than [`max_bytes`] allows
+    /// may still match. As a [`Matcher`], a file is searched no further
+    /// than its first matching line, so a binary file may also match if
+    /// that line comes before any NUL byte.
+    ///
+    /// [`Matcher`]: trait.Matcher.html
+    ///
+    /// [`max_bytes`]: #method.max_bytes
+    pub fn skip_binary(mut self, yes: bool) -> Self {
+        self.skip_binary = yes;
+        self
+    }
+
+    /// Searches no more than the first `limit` bytes of each file. By
+    /// default, files are searched in full.
+    pub fn max_bytes(mut self, limit: u64) -> Self {
+        self.max_bytes = Some(limit);
+        self
+    }
+
+    /// Searches the file at `path`, returning each matching line in order.
+    pub fn search<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<LineMatch>> {
+        self.scan(path.as_ref(), usize::MAX)
+    }
+
+    /// Searches the file at `path`, stopping after `limit` matching lines.
+    fn scan(&self, path: &Path, limit: usize) -> io::Result<Vec<LineMatch>> {
+        let file = File::open(path)?;
+        let mut reader = match self.max_bytes {
+            Some(max) => BufReader::new(Box::new(file.take(max)) as Box<dyn Read>),
+            None => BufReader::new(Box::new(file) as Box<dyn Read>),
+        };
+        // Check the first block up front, so that binary files are usually
+        // rejected before any of their lines are searched.
+        if self.skip_binary && reader.fill_buf()?.contains(&0) {
+            return Ok(Vec::new());
+        }
+        let mut matches = Vec::new();
+        let mut line = Vec::new();
+        let mut offset = 0;
+        let mut line_number = 0;
+        loop {
+            line.clear();
+            let mut len = (&mut reader)
+                .take(MAX_LINE_LEN)
+                .read_until(b'\n', &mut line)?;
+            if len == 0 {
+                return Ok(matches);
+            }
+            line_number += 1;
+            let mut binary = line.contains(&0);
+            if !line.ends_with(b"\n") && len as u64 == MAX_LINE_LEN {
+                let (rest, rest_binary) = skip_line(&mut reader)?;
+                len += rest;
+                binary |= rest_binary;
+            }
+            if self.skip_binary && binary {
+                return Ok(Vec::new());
+            }
+            let text = line
+                .strip_suffix(b"\n")
+                .map(|text| text.strip_suffix(b"\r").unwrap_or(text))
+                .unwrap_or(&line);
+            if let Some(m) = self.re.find(text) {
+                matches.push(LineMatch {
+                    line_number,
+                    byte_offset: offset + m.start() as u64,
+                    line: text.to_vec(),
+                });
+                if matches.len() >= limit {
+                    return Ok(matches);
+                }
+            }
+            offset += len as u64;
+        }
+    }
+}
+
+/// Reads past the rest of a line without keeping it, returning its length
+/// and whether it contains a NUL byte.
+fn skip_line(reader: &mut impl BufRead) -> io::Result<(usize, bool)> {
+    let (mut len, mut binary) = (0, false);
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok((len, binary));
+        }
+        let (used, done) = match buf.iter().position(|&b| b == b'\n') {
+            Some(i) => (i + 1, true),
+            None => (buf.len(), false),
+        };
+        binary |= buf[..used].contains(&0);
+        reader.consume(used);
+        len += used;
+        if done {
+            return Ok((len, binary));
+        }
+    }
+}
+
+impl Matcher for Contents {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        if !entry.file_type().is_file() {
+            return Ok(false);
+        }
+        let matches = self
+            .scan(entry.path(), 1)
+            .map_err(|err| io_error(entry, err))?;
+        Ok(!matches.is_empty())
+    }
+}
+
+/// A line of a file which matched a [`Contents`] search.
+///
+/// [`Contents`]: struct.Contents.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineMatch {
+    line_number: u64,
+    byte_offset: u64,
+    line: Vec<u8>,
+}
+
+impl LineMatch {
+    /// Returns the number of the line, starting from 1.
+    pub fn line_number(&self) -> u64 {
+        self.line_number
+    }
+
+    /// Returns the offset of the first match on the line, in bytes from
+    /// the start of the file.
+    pub fn byte_offset(&self) -> u64 {
+        self.byte_offset
+    }
+
+    /// Returns the contents of the line, without its line terminator.
+    pub fn line(&self) -> &[u8] {
+        &self.line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::dir_entry;
+    use tempdir::TempDir;
+
+    #[test]
+    fn line_numbers_and_offsets() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let path = tmp_dir.path().join("Cargo.toml");
+        std::fs::write(
+            &path,
+            "[package]\r\nname = \"a\"\n\n[workspace]\nmembers = []\n",
+        )
+        .unwrap();
+
+        let found = Contents::regex(r"^\[\w+\]$")
+            .unwrap()
+            .search(&path)
+            .unwrap();
+        let lines: Vec<_> = found
+            .iter()
+            .map(|m| (m.line_number(), m.byte_offset(), m.line()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![(1, 0, &b"[package]"[..]), (4, 23, &b"[workspace]"[..])]
+        );
+        let members = Contents::literal("= []").search(&path).unwrap();
+        assert_eq!(members[0].byte_offset(), 43);
+        assert!(Contents::literal("members")
+            .max_bytes(30)
+            .search(&path)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn case_fold() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let path = tmp_dir.path().join("README");
+        std::fs::write(&path, "Stra\u{df}e\nTODO\n").unwrap();
+        let lines = |contents: Contents| contents.search(&path).unwrap().len();
+
+        assert_eq!(lines(Contents::literal("todo")), 0);
+        assert_eq!(
+            lines(Contents::literal("todo").case_fold(CaseFold::Ascii)),
+            1
+        );
+        let street = || Contents::literal("STRA\u{1e9e}E");
+        assert_eq!(lines(street().case_fold(CaseFold::Ascii)), 0);
+        assert_eq!(lines(street().case_fold(CaseFold::Unicode)), 1);
+    }
+
+    #[test]
+    fn binary_files() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let path = tmp_dir.path().join("a.bin");
+        std::fs::write(&path, b"needle\n\0\x01\x02").unwrap();
+
+        assert!(Contents::literal("needle")
+            .search(&path)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            Contents::literal("needle")
+                .skip_binary(false)
+                .search(&path)
+                .unwrap()
+                .len(),
+            1
+        );
+        // As a matcher, a file is read no further than its first match, so
+        // a NUL byte beyond the first block is never seen.
+        let mut late = b"needle\n".to_vec();
+        late.extend_from_slice(&[b'x'; 1 << 16]);
+        late.push(0);
+        std::fs::write(tmp_dir.path().join("late.bin"), late).unwrap();
+        let entry = dir_entry(tmp_dir.path(), "late.bin");
+        assert!(Contents::literal("needle").is_match(&entry).unwrap());
+        let path = tmp_dir.path().join("late.bin");
+        assert!(Contents::literal("needle").search(path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn long_lines() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let path = tmp_dir.path().join("long.txt");
+        let mut contents = vec![b'x'; MAX_LINE_LEN as usize];
+        contents.extend_from_slice(b"late\nneedle\n");
+        std::fs::write(&path, &contents).unwrap();
+
+        // Only the start of a long line is searched, and the lines after
+        // it are still numbered and located correctly.
+        assert!(Contents::literal("late").search(&path).unwrap().is_empty());
+        let found = Contents::literal("needle").search(&path).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].line_number(), 2);
+        assert_eq!(found[0].byte_offset(), MAX_LINE_LEN + 5);
+    }
+}
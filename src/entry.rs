@@ -0,0 +1,101 @@
+use crate::matcher::io_error;
+use crate::Result;
+use std::ffi::OsStr;
+use std::fs::{self, FileType, Metadata};
+use std::path::{Path, PathBuf};
+
+/// An entry found while searching a directory tree.
+///
+/// This mirrors [`walkdir::DirEntry`], from which it may be converted, so
+/// that entries found by a parallel search, which does not use walkdir,
+/// can be described in the same way.
+///
+/// [`walkdir::DirEntry`]: https://docs.rs/walkdir/latest/walkdir/struct.DirEntry.html
+#[derive(Clone, Debug)]
+pub struct DirEntry {
+    path: PathBuf,
+    file_type: FileType,
+    // Whether the entry is a symbolic link which was followed, so that
+    // `file_type` describes its target.
+    follow_link: bool,
+    depth: usize,
+}
+
+impl DirEntry {
+    pub(crate) fn new(path: PathBuf, file_type: FileType, follow_link: bool, depth: usize) -> Self {
+        DirEntry {
+            path,
+            file_type,
+            follow_link,
+            depth,
+        }
+    }
+
+    /// Returns the full path of the entry, which is the root of the search
+    /// joined with the names of each directory below it.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Converts the entry into its full path.
+    pub fn into_path(self) -> PathBuf {
+        self.path
+    }
+
+    /// Returns true if the entry was created from a symbolic link, whether
+    /// or not the link was followed.
+    pub fn path_is_symlink(&self) -> bool {
+        self.file_type.is_symlink() || self.follow_link
+    }
+
+    /// Reads the metadata of the entry, describing the target of a
+    /// symbolic link only if the link was followed.
+    pub fn metadata(&self) -> Result<Metadata> {
+        let md = if self.follow_link {
+            fs::metadata(&self.path)
+        } else {
+            fs::symlink_metadata(&self.path)
+        };
+        md.map_err(|err| io_error(self, err))
+    }
+
+    /// Returns the type of the entry, which describes the target of a
+    /// symbolic link only if the link was followed.
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    /// Returns the name of the entry, or its full path if it is a root
+    /// such as `/` or `..` which has no name.
+    pub fn file_name(&self) -> &OsStr {
+        self.path
+            .file_name()
+            .unwrap_or_else(|| self.path.as_os_str())
+    }
+
+    /// Returns the depth of the entry below the root of the search, at
+    /// which the root itself is at depth `0`.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+}
+
+impl From<walkdir::DirEntry> for DirEntry {
+    fn from(entry: walkdir::DirEntry) -> Self {
+        let file_type = entry.file_type();
+        let follow_link = entry.path_is_symlink() && !file_type.is_symlink();
+        let depth = entry.depth();
+        DirEntry::new(entry.into_path(), file_type, follow_link, depth)
+    }
+}
+
+/// Returns the entry at `relative` below `root`, as found by a walk from
+/// `root`, for tests of matchers which inspect entries directly.
+#[cfg(test)]
+pub(crate) fn dir_entry(root: &Path, relative: &str) -> DirEntry {
+    walkdir::WalkDir::new(root)
+        .into_iter()
+        .map(|e| DirEntry::from(e.unwrap()))
+        .find(|e| e.path() == root.join(relative))
+        .unwrap()
+}
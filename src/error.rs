@@ -0,0 +1,220 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A specialized `Result` type for searches which may fail.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// An error which may occur while searching for files.
+///
+/// Where possible, errors record the path which caused them and the depth
+/// of that path relative to the root of the search.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An I/O error, such as a directory which could not be read or a file
+    /// whose metadata could not be retrieved.
+    Io {
+        /// The path being accessed, if the error is tied to one.
+        path: Option<PathBuf>,
+        /// The depth of `path` below the root of the search, if known.
+        depth: Option<usize>,
+        /// The underlying error.
+        source: io::Error,
+    },
+    /// A symbolic link which points to one of its own ancestors, found
+    /// while following links.
+    Loop {
+        /// The ancestor the link points to.
+        ancestor: PathBuf,
+        /// The path of the link.
+        child: PathBuf,
+        /// The depth of `child` below the root of the search.
+        depth: usize,
+    },
+    /// A pattern which could not be compiled.
+    InvalidPattern {
+        /// The pattern, as provided by the caller.
+        pattern: String,
+        /// The reason the pattern is invalid.
+        source: Box<dyn error::Error + Send + Sync>,
+    },
+    /// A value, such as a size, which could not be parsed.
+    InvalidValue {
+        /// The value, as provided by the caller.
+        value: String,
+        /// A description of the values which are accepted.
+        expected: &'static str,
+    },
+    /// The search visited more entries than permitted by
+    /// [`Finder::max_visited`], and stopped before completing.
+    ///
+    /// [`Finder::max_visited`]: struct.Finder.html#method.max_visited
+    BudgetExceeded {
+        /// The number of entries the search was permitted to visit.
+        limit: usize,
+        /// The first entry which was not visited.
+        path: PathBuf,
+        /// The depth of `path` below the root of the search.
+        depth: usize,
+    },
+}
+
+impl Error {
+    pub(crate) fn invalid_pattern<E>(pattern: &str, source: E) -> Self
+    where
+        E: error::Error + Send + Sync + 'static,
+    {
+        Error::InvalidPattern {
+            pattern: pattern.to_string(),
+            source: Box::new(source),
+        }
+    }
+
+    /// Returns the path associated with this error, if any.
+    ///
+    /// For [`Error::Loop`], this is the path of the link.
+    ///
+    /// [`Error::Loop`]: enum.Error.html#variant.Loop
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::Io { path, .. } => path.as_deref(),
+            Error::Loop { child, .. } => Some(child),
+            Error::InvalidPattern { .. } | Error::InvalidValue { .. } => None,
+            Error::BudgetExceeded { path, .. } => Some(path),
+        }
+    }
+
+    /// Returns the depth of the path associated with this error, relative
+    /// to the root of the search, if known.
+    pub fn depth(&self) -> Option<usize> {
+        match self {
+            Error::Io { depth, .. } => *depth,
+            Error::Loop { depth, .. } | Error::BudgetExceeded { depth, .. } => Some(*depth),
+            Error::InvalidPattern { .. } | Error::InvalidValue { .. } => None,
+        }
+    }
+
+    /// Returns the kind of the underlying I/O error, if this error was
+    /// caused by one.
+    pub fn io_error_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            Error::Io { source, .. } => Some(source.kind()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io {
+                path: Some(path),
+                source,
+                ..
+            } => write!(f, "{}: {}", path.display(), source),
+            Error::Io {
+                path: None, source, ..
+            } => source.fmt(f),
+            Error::Loop {
+                ancestor, child, ..
+            } => write!(
+                f,
+                "{}: filesystem loop, link points to ancestor {}",
+                child.display(),
+                ancestor.display()
+            ),
+            Error::InvalidPattern { pattern, source } => {
+                write!(f, "invalid pattern {:?}: {}", pattern, source)
+            }
+            Error::InvalidValue { value, expected } => {
+                write!(f, "invalid value {:?}: expected {}", value, expected)
+            }
+            Error::BudgetExceeded { limit, path, .. } => write!(
+                f,
+                "{}: search stopped after visiting {} entries",
+                path.display(),
+                limit
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::InvalidPattern { source, .. } => Some(source.as_ref()),
+            Error::Loop { .. } | Error::InvalidValue { .. } | Error::BudgetExceeded { .. } => None,
+        }
+    }
+}
+
+impl From<walkdir::Error> for Error {
+    fn from(err: walkdir::Error) -> Self {
+        let depth = err.depth();
+        let path = err.path().map(Path::to_path_buf);
+        if let (Some(ancestor), Some(child)) = (err.loop_ancestor(), &path) {
+            return Error::Loop {
+                ancestor: ancestor.to_path_buf(),
+                child: child.clone(),
+                depth,
+            };
+        }
+        Error::Io {
+            path,
+            depth: Some(depth),
+            // Every walkdir error which is not a loop wraps an I/O error.
+            source: err
+                .into_io_error()
+                .unwrap_or_else(|| io::Error::other("unknown directory walk error")),
+        }
+    }
+}
+
+/// Describes how a search reacts to errors, such as unreadable
+/// directories or files which vanish while the search is in progress.
+///
+/// Regardless of the policy, errors other than [`Error::BudgetExceeded`]
+/// never end a search early.
+///
+/// [`Error::BudgetExceeded`]: enum.Error.html#variant.BudgetExceeded
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Discard errors and continue searching.
+    #[default]
+    Skip,
+    /// Continue searching, recording each error so it may be inspected
+    /// once iteration is complete.
+    Collect,
+    /// Return errors to the caller as they occur.
+    ///
+    /// Errors are only returned by the fallible iterator created with
+    /// [`Finder::try_iter`]; the infallible iterator cannot return them,
+    /// and records them as with [`ErrorPolicy::Collect`] instead.
+    ///
+    /// [`Finder::try_iter`]: struct.Finder.html#method.try_iter
+    /// [`ErrorPolicy::Collect`]: enum.ErrorPolicy.html#variant.Collect
+    Yield,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_error_context() {
+        let err: Error = walkdir::WalkDir::new("/does/not/exist")
+            .into_iter()
+            .next()
+            .unwrap()
+            .unwrap_err()
+            .into();
+
+        assert_eq!(err.path(), Some(Path::new("/does/not/exist")));
+        assert_eq!(err.depth(), Some(0));
+        assert_eq!(err.io_error_kind(), Some(io::ErrorKind::NotFound));
+        assert!(err.to_string().starts_with("/does/not/exist: "));
+    }
+}
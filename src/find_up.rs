@@ -0,0 +1,244 @@
+use crate::{DirEntry, Pattern};
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+use std::vec;
+use walkdir::WalkDir;
+
+/// A point beyond which a [`FindUp`] search does not continue.
+///
+/// [`FindUp`]: struct.FindUp.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// Search every ancestor, up to and including the filesystem root.
+    Root,
+    /// Search up to and including the given directory.
+    ///
+    /// If the directory is not an ancestor of the starting point, this
+    /// boundary has no effect.
+    Dir(PathBuf),
+    /// Search up to and including the current user's home directory, as
+    /// given by the `HOME` (or, on Windows, `USERPROFILE`) environment
+    /// variable.
+    Home,
+    /// Search up to and including the first directory which contains an
+    /// entry with the given name, such as `.git` to remain within a
+    /// repository.
+    Marker(String),
+}
+
+/// Searches a directory and each of its ancestors for a file, such as
+/// the nearest `Cargo.toml` or `.editorconfig`.
+///
+/// Where [`Finder`] searches downward from a root, `FindUp` searches the
+/// immediate contents of its starting directory, then of its parent, and
+/// so on. Matches are yielded nearest-first, and in order of file name
+/// within a single directory.
+///
+/// Paths are resolved lexically: a relative starting point is joined to
+/// the current directory, and `..` removes the preceding component rather
+/// than following symbolic links.
+///
+/// ```no_run
+/// use where_is::{Boundary, FindUp};
+///
+/// let manifest = FindUp::new(".", "Cargo.toml")
+///     .stop_at(Boundary::Marker(".git".to_string()))
+///     .into_iter()
+///     .next();
+/// ```
+///
+/// [`Finder`]: struct.Finder.html
+#[derive(Clone, Debug)]
+pub struct FindUp {
+    start: PathBuf,
+    pattern: Pattern,
+    boundaries: Vec<Boundary>,
+}
+
+impl FindUp {
+    /// Constructs a new `FindUp` object, which searches `start` and its
+    /// ancestors for an entry named `target`.
+    pub fn new<P: AsRef<Path>, S: AsRef<OsStr>>(start: P, target: S) -> Self {
+        Self::with_pattern(start, Pattern::exact(target))
+    }
+
+    /// Constructs a new `FindUp` object, which searches `start` and its
+    /// ancestors for entries which match `pattern`.
+    ///
+    /// Entries are considered to be one level below the directory being
+    /// searched, so [`MatchOn::RelativePath`] is equivalent to
+    /// [`MatchOn::FileName`].
+    ///
+    /// [`MatchOn::RelativePath`]: enum.MatchOn.html#variant.RelativePath
+    /// [`MatchOn::FileName`]: enum.MatchOn.html#variant.FileName
+    pub fn with_pattern<P: AsRef<Path>>(start: P, pattern: Pattern) -> Self {
+        FindUp {
+            start: absolute(start.as_ref()),
+            pattern,
+            boundaries: Vec::new(),
+        }
+    }
+
+    /// Stops the search at `boundary`.
+    ///
+    /// May be called multiple times, in which case the search stops at
+    /// whichever boundary is reached first. Without any boundary, the
+    /// search continues to the filesystem root.
+    pub fn stop_at(mut self, boundary: Boundary) -> Self {
+        let boundary = match boundary {
+            Boundary::Dir(dir) => Boundary::Dir(absolute(&dir)),
+            other => other,
+        };
+        self.boundaries.push(boundary);
+        self
+    }
+}
+
+impl IntoIterator for FindUp {
+    type Item = DirEntry;
+    type IntoIter = FindUpIter;
+
+    fn into_iter(self) -> FindUpIter {
+        let home = std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| absolute(Path::new(&home)));
+        FindUpIter {
+            next_dir: Some(self.start),
+            pattern: self.pattern,
+            boundaries: self.boundaries,
+            home,
+            found: Vec::new().into_iter(),
+        }
+    }
+}
+
+/// An iterator over the matches of a [`FindUp`] search, nearest-first.
+///
+/// Directories which cannot be read are skipped.
+///
+/// [`FindUp`]: struct.FindUp.html
+pub struct FindUpIter {
+    next_dir: Option<PathBuf>,
+    pattern: Pattern,
+    boundaries: Vec<Boundary>,
+    home: Option<PathBuf>,
+    found: vec::IntoIter<DirEntry>,
+}
+
+impl FindUpIter {
+    fn is_boundary(&self, dir: &Path) -> bool {
+        self.boundaries.iter().any(|boundary| match boundary {
+            Boundary::Root => false,
+            Boundary::Dir(boundary) => dir == boundary,
+            Boundary::Home => self.home.as_deref() == Some(dir),
+            Boundary::Marker(name) => dir.join(name).symlink_metadata().is_ok(),
+        })
+    }
+}
+
+impl Iterator for FindUpIter {
+    type Item = DirEntry;
+
+    fn next(&mut self) -> Option<DirEntry> {
+        loop {
+            if let Some(dent) = self.found.next() {
+                return Some(dent);
+            }
+            let dir = self.next_dir.take()?;
+            let pattern = &self.pattern;
+            self.found = WalkDir::new(&dir)
+                .min_depth(1)
+                .max_depth(1)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|dent| dent.ok())
+                .map(DirEntry::from)
+                .filter(|dent| pattern.matches(dent))
+                .collect::<Vec<_>>()
+                .into_iter();
+            if !self.is_boundary(&dir) {
+                self.next_dir = dir.parent().map(Path::to_path_buf);
+            }
+        }
+    }
+}
+
+/// Makes `path` absolute, resolving `.` and `..` components lexically.
+pub(crate) fn absolute(path: &Path) -> PathBuf {
+    let joined = match std::env::current_dir() {
+        Ok(cwd) if path.is_relative() => cwd.join(path),
+        _ => path.to_path_buf(),
+    };
+    let mut resolved = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other.as_os_str()),
+        }
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    fn find_up(finder: FindUp) -> Vec<PathBuf> {
+        finder.into_iter().map(DirEntry::into_path).collect()
+    }
+
+    #[test]
+    fn nearest_first() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path();
+        std::fs::create_dir_all(root.join("a/b/c")).unwrap();
+        std::fs::write(root.join("a/Cargo.toml"), "").unwrap();
+        std::fs::write(root.join("a/b/Cargo.toml"), "").unwrap();
+
+        let finder =
+            FindUp::new(root.join("a/b/c"), "Cargo.toml").stop_at(Boundary::Dir(root.into()));
+        assert_eq!(
+            find_up(finder),
+            vec![root.join("a/b/Cargo.toml"), root.join("a/Cargo.toml")]
+        );
+    }
+
+    #[test]
+    fn stop_at_marker() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path();
+        std::fs::create_dir_all(root.join("a/b/.git")).unwrap();
+        std::fs::create_dir_all(root.join("a/b/c/d")).unwrap();
+        std::fs::write(root.join("a/.editorconfig"), "").unwrap();
+        std::fs::write(root.join("a/b/c/.editorconfig"), "").unwrap();
+
+        let finder = FindUp::new(root.join("a/b/c/d/.."), ".editorconfig")
+            .stop_at(Boundary::Marker(".git".to_string()));
+        assert_eq!(find_up(finder), vec![root.join("a/b/c/.editorconfig")]);
+    }
+
+    #[test]
+    fn pattern_matches_within_each_ancestor() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path();
+        std::fs::create_dir_all(root.join("a")).unwrap();
+        for name in &["a/b.txt", "a/a.txt", "z.txt"] {
+            std::fs::write(root.join(name), "").unwrap();
+        }
+
+        let finder = FindUp::with_pattern(root.join("a"), Pattern::glob("*.txt").unwrap())
+            .stop_at(Boundary::Dir(root.into()));
+        assert_eq!(
+            find_up(finder),
+            vec![
+                root.join("a/a.txt"),
+                root.join("a/b.txt"),
+                root.join("z.txt")
+            ]
+        );
+    }
+}
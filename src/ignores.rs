@@ -0,0 +1,130 @@
+use crate::find_up::absolute;
+use crate::DirEntry;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The ignore files read from each directory, in increasing order of
+/// precedence.
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore", ".whereisignore"];
+
+/// The ignore rules in effect while walking beneath a single root, as
+/// enabled by [`Finder::ignore_files`].
+///
+/// Rules are matched against absolute paths, so that ignore files outside
+/// the root apply however the root was spelled. Cloning is cheap, since
+/// the rules themselves are shared, which lets a parallel search hand each
+/// directory the rules of its ancestors.
+///
+/// [`Finder::ignore_files`]: struct.Finder.html#method.ignore_files
+#[derive(Clone)]
+pub(crate) struct Ignores {
+    root: PathBuf,
+    abs_root: PathBuf,
+    // Rules from outside the walk, in decreasing order of precedence: the
+    // ignore files of the root's ancestors within its repository, the
+    // repository's `.git/info/exclude`, then the global excludes file.
+    outer: Arc<[Gitignore]>,
+    // The directories from the root down to the parent of the latest
+    // entry, with their rules and whether they are themselves ignored.
+    dirs: Vec<(PathBuf, Arc<Gitignore>, bool)>,
+}
+
+impl Ignores {
+    pub(crate) fn new(root: &Path) -> Self {
+        let abs_root = absolute(root);
+        let mut outer = Vec::new();
+        if let Some(repo) = abs_root.ancestors().find(|dir| dir.join(".git").exists()) {
+            outer.extend(
+                abs_root
+                    .ancestors()
+                    .skip(1)
+                    .take_while(|dir| dir.starts_with(repo))
+                    .map(load),
+            );
+            let mut builder = GitignoreBuilder::new(repo);
+            builder.add(repo.join(".git/info/exclude"));
+            outer.push(builder.build().unwrap_or_else(|_| Gitignore::empty()));
+        }
+        outer.push(Gitignore::global().0);
+        Ignores {
+            root: root.to_path_buf(),
+            abs_root,
+            outer: outer.into(),
+            dirs: Vec::new(),
+        }
+    }
+
+    /// Returns true if `entry`, or one of its ancestors below the root, is
+    /// ignored. The root itself never is.
+    pub(crate) fn is_ignored(&mut self, entry: &DirEntry) -> bool {
+        if entry.depth() == 0 {
+            return false;
+        }
+        let path = match entry.path().strip_prefix(&self.root) {
+            Ok(relative) => self.abs_root.join(relative),
+            Err(_) => return false,
+        };
+        let parent = path.parent().unwrap_or(&path);
+        self.enter(parent);
+        if self.dirs.last().is_some_and(|&(_, _, ignored)| ignored) {
+            return true;
+        }
+        self.matched(&path, entry.file_type().is_dir())
+    }
+
+    /// Brings the rules of `dir` and each of its ancestors below the root
+    /// into effect, discarding those of any other directory.
+    fn enter(&mut self, dir: &Path) {
+        while let Some((top, _, _)) = self.dirs.last() {
+            if dir.starts_with(top) {
+                break;
+            }
+            self.dirs.pop();
+        }
+        let top = self.dirs.last().map(|(top, _, _)| top.clone());
+        let missing: Vec<PathBuf> = dir
+            .ancestors()
+            .take_while(|d| Some(*d) != top.as_deref() && d.starts_with(&self.abs_root))
+            .map(Path::to_path_buf)
+            .collect();
+        for dir in missing.into_iter().rev() {
+            let ignored = dir != self.abs_root
+                && (self.dirs.last().is_some_and(|&(_, _, ignored)| ignored)
+                    || self.matched(&dir, true));
+            let rules = Arc::new(load(&dir));
+            self.dirs.push((dir, rules, ignored));
+        }
+    }
+
+    /// Matches `path` against the rules in effect, the nearest of which
+    /// take precedence.
+    fn matched(&self, path: &Path, is_dir: bool) -> bool {
+        // The repository itself is never searched, whatever the rules say.
+        if path.file_name().is_some_and(|name| name == ".git") {
+            return true;
+        }
+        let rules = self.dirs.iter().rev().map(|(_, rules, _)| rules);
+        for rules in rules.map(|rules| &**rules).chain(self.outer.iter()) {
+            match rules.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
+
+/// Reads the ignore files of `dir`, skipping any malformed lines.
+fn load(dir: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(dir);
+    for name in IGNORE_FILES {
+        let path = dir.join(name);
+        if path.is_file() {
+            builder.add(path);
+        }
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
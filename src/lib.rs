@@ -13,78 +13,1263 @@ To use this crate, add `where_is` as a dependency to your project's
 
 #![deny(missing_docs)]
 
-use std::path::Path;
-use walkdir::{DirEntry, Result, WalkDir};
+mod contents;
+mod entry;
+mod error;
+mod find_up;
+mod ignores;
+mod matcher;
+mod parallel;
+mod pattern;
+mod search;
+mod sort;
+#[cfg(unix)]
+mod unix;
+mod walk;
+mod which;
+
+pub use contents::{Contents, LineMatch};
+pub use entry::DirEntry;
+pub use error::{Error, ErrorPolicy, Result};
+pub use find_up::{Boundary, FindUp, FindUpIter};
+pub use matcher::{
+    all_of, any_of, AllOf, And, AnyOf, Empty, EntryType, Extension, Hidden, HiddenPolicy,
+    LinkTarget, Matcher, Not, Or, Size, Time, Timestamp, Under,
+};
+pub use parallel::ParWalk;
+pub use pattern::{CaseFold, MatchOn, Normalization, Pattern};
+pub use sort::SortKey;
+#[cfg(unix)]
+pub use unix::{Access, Group, Mode, NoOwner, Owner};
+pub use walk::{RootOrder, Walk};
+pub use which::{which, Which, WhichIter};
+
+use search::{BreadthFirst, Limits, Search};
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::thread;
+use std::vec;
+use walk::{DirLimits, FileId, Prune, WalkOptions};
 
 /// A file-finding structure.
 ///
-/// Wraps an underlying [`walkdir::WalkDir`] object, and pairs
-/// it with a target string used for filtering.
+/// Configures an underlying [`walkdir::WalkDir`] object, and pairs
+/// it with a [`Pattern`] used for filtering.
 ///
 /// [`walkdir::WalkDir`]: https://docs.rs/walkdir/latest/walkdir/struct.WalkDir.html
+/// [`Pattern`]: struct.Pattern.html
 pub struct Finder {
-    walker: WalkDir,
-    target: String,
+    roots: Vec<PathBuf>,
+    root_order: RootOrder,
+    dedup: bool,
+    options: WalkOptions,
+    pattern: Pattern,
+    filters: Vec<Box<dyn Matcher>>,
+    prunes: Vec<Box<dyn Matcher>>,
+    types: Vec<EntryType>,
+    hidden: HiddenPolicy,
+    skip_hidden_dirs: bool,
+    error_policy: ErrorPolicy,
+    max_visited: Option<usize>,
+    max_results: Option<usize>,
+    max_results_per_dir: Option<usize>,
+    max_results_per_subtree: Option<(usize, usize)>,
+    threads: usize,
+    deterministic: bool,
+    sort_results: Option<SortKey>,
 }
 
 impl Finder {
     /// Constructs a new `Finder` object, which walks the directory tree
     /// from `root`, looking for a file which matches `target`.
-    // TODO(smklein): Regex, instead of target?
-    pub fn new<P: AsRef<Path>>(root: P, target: &str) -> Self {
+    ///
+    /// Names are compared byte-for-byte, so `target` need not be valid
+    /// UTF-8.
+    pub fn new<P: AsRef<Path>, S: AsRef<OsStr>>(root: P, target: S) -> Self {
+        Self::with_pattern(root, Pattern::exact(target))
+    }
+
+    /// Constructs a new `Finder` object, which walks the directory tree
+    /// from `root`, looking for files named any of `targets`, in a single
+    /// pass.
+    ///
+    /// Use [`Finder::matches`] to learn which target each entry matched.
+    ///
+    /// [`Finder::matches`]: struct.Finder.html#method.matches
+    pub fn with_targets<P, I, S>(root: P, targets: I) -> Self
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        Self::with_pattern(root, Pattern::names(targets))
+    }
+
+    /// Constructs a new `Finder` object, which walks the directory tree
+    /// from `root`, looking for files whose names match the regular
+    /// expression `re`.
+    ///
+    /// To match against the path relative to `root` instead of the file
+    /// name, construct a [`Pattern`] and use [`Finder::with_pattern`].
+    ///
+    /// [`Pattern`]: struct.Pattern.html
+    /// [`Finder::with_pattern`]: struct.Finder.html#method.with_pattern
+    pub fn with_regex<P: AsRef<Path>>(root: P, re: &str) -> Result<Self> {
+        Ok(Self::with_pattern(root, Pattern::regex(re)?))
+    }
+
+    /// Constructs a new `Finder` object, which walks the directory tree
+    /// from `root`, looking for files which match the shell glob `glob`.
+    ///
+    /// See [`Pattern::glob`] for the supported syntax.
+    ///
+    /// [`Pattern::glob`]: struct.Pattern.html#method.glob
+    pub fn with_glob<P: AsRef<Path>>(root: P, glob: &str) -> Result<Self> {
+        Ok(Self::with_pattern(root, Pattern::glob(glob)?))
+    }
+
+    /// Constructs a new `Finder` object, which walks the directory tree
+    /// from `root`, looking for files which match `pattern`.
+    pub fn with_pattern<P: AsRef<Path>>(root: P, pattern: Pattern) -> Self {
         Finder {
-            walker: WalkDir::new(root),
-            target: target.to_string(),
+            roots: vec![root.as_ref().to_path_buf()],
+            root_order: RootOrder::default(),
+            dedup: false,
+            options: WalkOptions::default(),
+            pattern,
+            filters: Vec::new(),
+            prunes: Vec::new(),
+            types: Vec::new(),
+            hidden: HiddenPolicy::default(),
+            skip_hidden_dirs: false,
+            error_policy: ErrorPolicy::default(),
+            max_visited: None,
+            max_results: None,
+            max_results_per_dir: None,
+            max_results_per_subtree: None,
+            threads: 0,
+            deterministic: false,
+            sort_results: None,
+        }
+    }
+
+    /// Constructs a new `Finder` object, which walks the directory tree
+    /// from `root`, looking for entries which satisfy `matcher`.
+    ///
+    /// Unlike the other constructors, this places no restriction on names.
+    pub fn with_matcher<P, M>(root: P, matcher: M) -> Self
+    where
+        P: AsRef<Path>,
+        M: Matcher + 'static,
+    {
+        Self::with_pattern(root, Pattern::any()).matching(matcher)
+    }
+
+    /// Restricts the search to entries which also satisfy `matcher`.
+    ///
+    /// May be called multiple times; entries must satisfy every matcher,
+    /// as well as the `Finder`'s pattern.
+    pub fn matching<M: Matcher + 'static>(mut self, matcher: M) -> Self {
+        self.filters.push(Box::new(matcher));
+        self
+    }
+
+    /// Restricts the search to entries of type `entry_type`, such as only
+    /// directories named `tests`.
+    ///
+    /// May be called multiple times to accept entries of any of the given
+    /// types. See [`EntryType`] for how symbolic links are treated.
+    ///
+    /// [`EntryType`]: enum.EntryType.html
+    pub fn entry_type(mut self, entry_type: EntryType) -> Self {
+        self.types.push(entry_type);
+        self
+    }
+
+    /// Skips the contents of directories which satisfy `matcher`.
+    ///
+    /// Unlike [`Finder::matching`], which only decides whether an entry
+    /// is yielded, pruning avoids reading the directory at all, so the
+    /// search never pays for what lies below it:
+    ///
+    /// ```no_run
+    /// use where_is::{Finder, Pattern};
+    ///
+    /// let finder = Finder::new(".", "index.js")
+    ///     .prune(Pattern::names(["node_modules", ".git", "target"]));
+    /// ```
+    ///
+    /// A pruned directory is still yielded if it matches the search. May be
+    /// called multiple times; directories satisfying any of the matchers
+    /// are pruned.
+    ///
+    /// [`Finder::matching`]: struct.Finder.html#method.matching
+    pub fn prune<M: Matcher + 'static>(mut self, matcher: M) -> Self {
+        self.prunes.push(Box::new(matcher));
+        self
+    }
+
+    /// Limits the search to visiting at most `limit` entries, matching or
+    /// not.
+    ///
+    /// If the limit is reached before the search completes, the search
+    /// stops and reports [`Error::BudgetExceeded`] according to the
+    /// [`ErrorPolicy`].
+    ///
+    /// [`Error::BudgetExceeded`]: enum.Error.html#variant.BudgetExceeded
+    /// [`ErrorPolicy`]: enum.ErrorPolicy.html
+    pub fn max_visited(mut self, limit: usize) -> Self {
+        self.max_visited = Some(limit);
+        self
+    }
+
+    /// Stops the search as soon as `n` matches have been yielded.
+    ///
+    /// Unlike taking the first `n` results of the search, this also bounds
+    /// the work done by parallel searches and by [`Finder::par_for_each`].
+    /// When sorting with [`Finder::sort_results`], the first `n` results
+    /// in the sorted order are yielded, so the whole tree is still
+    /// searched.
+    ///
+    /// [`Finder::par_for_each`]: struct.Finder.html#method.par_for_each
+    /// [`Finder::sort_results`]: struct.Finder.html#method.sort_results
+    pub fn max_results(mut self, n: usize) -> Self {
+        self.max_results = Some(n);
+        self
+    }
+
+    /// Yields at most `n` matches from the entries of each directory,
+    /// skipping the rest of a directory, including its subdirectories, once
+    /// that many have been found in it. The contents of a matching
+    /// directory are still searched, and may yield matches of their own.
+    ///
+    /// This samples trees too large to search in full, such as a dataset
+    /// with a directory of many thousands of images for each class:
+    ///
+    /// ```no_run
+    /// use where_is::Finder;
+    ///
+    /// let samples: Vec<_> = Finder::with_glob("dataset", "*.jpg")?
+    ///     .max_results_per_dir(10)
+    ///     .into_iter()
+    ///     .collect();
+    /// # Ok::<(), where_is::Error>(())
+    /// ```
+    ///
+    /// Which entries are sampled depends on the order in which each
+    /// directory is read, which may be set with [`Finder::sort_by`]. With
+    /// [`Finder::contents_first`], nothing is skipped, and further matches
+    /// are only left out of the results.
+    ///
+    /// [`Finder::sort_by`]: struct.Finder.html#method.sort_by
+    /// [`Finder::contents_first`]: struct.Finder.html#method.contents_first
+    pub fn max_results_per_dir(mut self, n: usize) -> Self {
+        self.max_results_per_dir = Some(n);
+        self
+    }
+
+    /// Yields at most `n` matches from below each entry at `depth`,
+    /// skipping the rest of its subtree once that many have been found in
+    /// it.
+    ///
+    /// Where [`Finder::max_results_per_dir`] samples each directory, this
+    /// samples whole subtrees, such as each class of a dataset whose images
+    /// are spread over many nested directories:
+    ///
+    /// ```no_run
+    /// use where_is::Finder;
+    ///
+    /// let samples: Vec<_> = Finder::with_glob("dataset", "*.jpg")?
+    ///     .max_results_per_subtree(1, 100)
+    ///     .into_iter()
+    ///     .collect();
+    /// # Ok::<(), where_is::Error>(())
+    /// ```
+    ///
+    /// Matches at or above `depth` are not limited. As with
+    /// [`Finder::max_results_per_dir`], which entries are sampled depends
+    /// on the order in which directories are read.
+    ///
+    /// [`Finder::max_results_per_dir`]: struct.Finder.html#method.max_results_per_dir
+    pub fn max_results_per_subtree(mut self, depth: usize, n: usize) -> Self {
+        self.max_results_per_subtree = Some((depth, n));
+        self
+    }
+
+    /// Adds another directory tree to search, rooted at `root`.
+    ///
+    /// All traversal options apply to each root independently: depths,
+    /// for example, are measured from whichever root an entry was found
+    /// under.
+    pub fn add_root<P: AsRef<Path>>(mut self, root: P) -> Self {
+        self.roots.push(root.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets the order in which entries from different roots are yielded.
+    ///
+    /// Defaults to [`RootOrder::Sequential`].
+    ///
+    /// [`RootOrder::Sequential`]: enum.RootOrder.html#variant.Sequential
+    pub fn root_order(mut self, order: RootOrder) -> Self {
+        self.root_order = order;
+        self
+    }
+
+    /// Yields each file at most once, even if it is reachable from more
+    /// than one root, or through symbolic links.
+    ///
+    /// Files are identified after resolving symbolic links, so a link and
+    /// its target count as the same file; only the first of them to be
+    /// found is yielded. Disabled by default, since identifying each match
+    /// requires reading its metadata.
+    ///
+    /// Which of several paths is found first by a parallel search depends
+    /// on timing, unless [`Finder::deterministic`] is set.
+    ///
+    /// [`Finder::deterministic`]: struct.Finder.html#method.deterministic
+    pub fn dedup(mut self, yes: bool) -> Self {
+        self.dedup = yes;
+        self
+    }
+
+    /// Only yields entries at least `depth` levels below the root.
+    ///
+    /// The root itself is at depth `0`, its children at depth `1`, and so
+    /// on. Entries above the minimum depth are still traversed, so a
+    /// minimum depth does not make a search any faster, and directories
+    /// among them are still pruned.
+    ///
+    /// If the minimum depth exceeds the maximum depth, the search yields
+    /// nothing.
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.options.min_depth = depth;
+        self
+    }
+
+    /// Only yields entries at most `depth` levels below the root.
+    ///
+    /// Directories at the maximum depth are not read at all, so this
+    /// bounds the cost of searching very large trees. By default, there is
+    /// no maximum depth.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.options.max_depth = depth;
+        self
+    }
+
+    /// Follows symbolic links, searching their targets as though they were
+    /// part of the tree.
+    ///
+    /// Yielded entries keep the path of the link, but describe its target.
+    /// Broken links and links which point to one of their own ancestors
+    /// are reported as errors. By default, links are not followed, although
+    /// a root which is a link is always resolved.
+    pub fn follow_links(mut self, yes: bool) -> Self {
+        self.options.follow_links = yes;
+        self
+    }
+
+    /// Stays on the filesystem of the root, never descending into
+    /// directories which are mount points for other filesystems, such as
+    /// network shares.
+    ///
+    /// Disabled by default.
+    pub fn same_file_system(mut self, yes: bool) -> Self {
+        self.options.same_file_system = yes;
+        self
+    }
+
+    /// Limits the number of directories held open at once during the
+    /// search.
+    ///
+    /// Once the limit is reached, the remaining entries of the oldest open
+    /// directory are buffered in memory instead. This never changes which
+    /// entries are found, and a limit of `0` is treated as `1`. Defaults to
+    /// `10`.
+    pub fn max_open(mut self, n: usize) -> Self {
+        self.options.max_open = n;
+        self
+    }
+
+    /// Yields the contents of each directory before the directory itself.
+    ///
+    /// Pruned and ignored directories are still read, since walkdir lists
+    /// a directory's contents before yielding it, although their contents
+    /// are never yielded. Patterns and [`Finder::prune`] therefore cannot
+    /// make such a search any faster. Disabled by default.
+    ///
+    /// [`Finder::prune`]: struct.Finder.html#method.prune
+    pub fn contents_first(mut self, yes: bool) -> Self {
+        self.options.contents_first = yes;
+        self
+    }
+
+    /// Visits the entries of each directory in the order given by `key`,
+    /// rather than the order in which the filesystem lists them, which
+    /// differs between filesystems and machines.
+    ///
+    /// Each directory is read in full before any of its entries is
+    /// yielded, but results are still yielded as the search proceeds. With
+    /// [`SortKey::Name`], entries are yielded depth-first in order of name,
+    /// as `ls -R` would list them. Parallel searches read each directory in
+    /// this order too, but yield results as their threads find them; order
+    /// their results with [`Finder::deterministic`] or
+    /// [`Finder::sort_results`] instead.
+    ///
+    /// [`SortKey::Name`]: enum.SortKey.html#variant.Name
+    /// [`Finder::deterministic`]: struct.Finder.html#method.deterministic
+    /// [`Finder::sort_results`]: struct.Finder.html#method.sort_results
+    pub fn sort_by(mut self, key: SortKey) -> Self {
+        self.options.sort = Some(key);
+        self
+    }
+
+    /// Sorts every result of the search by `key`, such as the largest
+    /// files last, or the shallowest matches first.
+    ///
+    /// No results are yielded until the search is complete, and all of
+    /// them are held in memory until then. Errors are reported as they
+    /// occur, ahead of any results. When deduplicating with
+    /// [`Finder::dedup`], the first of several paths to a file in the
+    /// sorted order is kept.
+    ///
+    /// [`Finder::dedup`]: struct.Finder.html#method.dedup
+    pub fn sort_results(mut self, key: SortKey) -> Self {
+        self.sort_results = Some(key);
+        self
+    }
+
+    /// Searches breadth-first, reading every directory at one depth before
+    /// any directory below it, so that matches are yielded in order of
+    /// increasing depth.
+    ///
+    /// Directories waiting to be read are held in memory, along with the
+    /// state needed to read them, so a breadth-first search of a wide tree
+    /// uses more memory than a depth-first one. [`Finder::root_order`],
+    /// [`Finder::max_open`] and [`Finder::contents_first`] have no effect,
+    /// and all roots are searched together, level by level. Parallel
+    /// searches are unaffected. Disabled by default.
+    ///
+    /// [`Finder::root_order`]: struct.Finder.html#method.root_order
+    /// [`Finder::max_open`]: struct.Finder.html#method.max_open
+    /// [`Finder::contents_first`]: struct.Finder.html#method.contents_first
+    pub fn breadth_first(mut self, yes: bool) -> Self {
+        self.options.breadth_first = yes;
+        self
+    }
+
+    /// Stops a breadth-first search once the shallowest depth containing
+    /// any match has been searched, yielding every match at that depth and
+    /// none below it.
+    ///
+    /// This finds "the" file of a given name in a tree, such as the
+    /// top-level configuration of a project, without searching the rest of
+    /// the tree:
+    ///
+    /// ```no_run
+    /// use where_is::Finder;
+    ///
+    /// let config = Finder::new(".", "config.toml")
+    ///     .stop_at_shallowest(true)
+    ///     .into_iter()
+    ///     .next();
+    /// ```
+    ///
+    /// Implies [`Finder::breadth_first`]. Parallel searches are unaffected.
+    /// Disabled by default.
+    ///
+    /// [`Finder::breadth_first`]: struct.Finder.html#method.breadth_first
+    pub fn stop_at_shallowest(mut self, yes: bool) -> Self {
+        self.options.stop_at_shallowest = yes;
+        self
+    }
+
+    /// Skips entries excluded by ignore files, as other tools do when
+    /// searching a checkout. Disabled by default.
+    ///
+    /// Rules are read from the `.gitignore`, `.ignore` and `.whereisignore`
+    /// files of each directory searched, with later files taking
+    /// precedence, and apply to everything below that directory. When a
+    /// root is inside a git repository, the ignore files of its ancestors
+    /// within the repository and the repository's `.git/info/exclude` also
+    /// apply, as does the global `core.excludesFile` in all cases. Rules
+    /// follow gitignore syntax, including negation with `!`, directory-only
+    /// rules ending in `/` and rules anchored with a leading `/`; malformed
+    /// rules are skipped. Ignore files apply wherever they are found, even
+    /// outside a git repository.
+    ///
+    /// The `.git` directory of a repository is always skipped. Ignored
+    /// directories are not searched, and roots are never ignored.
+    pub fn ignore_files(mut self, yes: bool) -> Self {
+        self.options.ignore_files = yes;
+        self
+    }
+
+    /// Sets whether [`Hidden`] entries are yielded.
+    ///
+    /// This only filters results: hidden directories are still searched
+    /// unless [`Finder::skip_hidden_dirs`] is also set. Defaults to
+    /// [`HiddenPolicy::Include`].
+    ///
+    /// [`Hidden`]: struct.Hidden.html
+    /// [`Finder::skip_hidden_dirs`]: struct.Finder.html#method.skip_hidden_dirs
+    /// [`HiddenPolicy::Include`]: enum.HiddenPolicy.html#variant.Include
+    pub fn hidden(mut self, policy: HiddenPolicy) -> Self {
+        self.hidden = policy;
+        self
+    }
+
+    /// Skips the contents of [`Hidden`] directories, such as `.git` or
+    /// `.cache`, which are often large and rarely of interest. Disabled
+    /// by default.
+    ///
+    /// The hidden directories themselves may still be yielded, according
+    /// to [`Finder::hidden`].
+    ///
+    /// [`Hidden`]: struct.Hidden.html
+    /// [`Finder::hidden`]: struct.Finder.html#method.hidden
+    pub fn skip_hidden_dirs(mut self, yes: bool) -> Self {
+        self.skip_hidden_dirs = yes;
+        self
+    }
+
+    /// Sets how the search reacts to errors.
+    ///
+    /// Defaults to [`ErrorPolicy::Skip`].
+    ///
+    /// [`ErrorPolicy::Skip`]: enum.ErrorPolicy.html#variant.Skip
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Sets the number of threads which read directories during a parallel
+    /// search, started with [`Finder::par_iter`], [`Finder::par_try_iter`]
+    /// or [`Finder::par_for_each`].
+    ///
+    /// A count of `0`, the default, uses one thread for each CPU.
+    ///
+    /// [`Finder::par_iter`]: struct.Finder.html#method.par_iter
+    /// [`Finder::par_try_iter`]: struct.Finder.html#method.par_try_iter
+    /// [`Finder::par_for_each`]: struct.Finder.html#method.par_for_each
+    pub fn threads(mut self, n: usize) -> Self {
+        self.threads = n;
+        self
+    }
+
+    /// Makes a parallel search yield its results in the same order on
+    /// every run: roots in the order they were added, and the entries below
+    /// each root depth-first, in order of name.
+    ///
+    /// Results can only be ordered once the search is complete, so they
+    /// are held in memory and none is yielded until then. Disabled by
+    /// default, in which case results are yielded as soon as they are
+    /// found, in no particular order.
+    pub fn deterministic(mut self, yes: bool) -> Self {
+        self.deterministic = yes;
+        self
+    }
+
+    /// Converts the `Finder` into a fallible iterator, which returns
+    /// errors alongside matching entries when using
+    /// [`ErrorPolicy::Yield`].
+    ///
+    /// This makes it possible to distinguish a search which found
+    /// nothing from a search which was unable to look everywhere.
+    ///
+    /// [`ErrorPolicy::Yield`]: enum.ErrorPolicy.html#variant.Yield
+    pub fn try_iter(mut self) -> TryIter<Walk, EntryFilter> {
+        if self.options.breadth_first || self.options.stop_at_shallowest {
+            let ordered = self.sort_results.is_some();
+            return self.searched(ordered, |search| {
+                Walk::breadth_first(BreadthFirst::new(search))
+            });
+        }
+        let query = Rc::new(self.take_query());
+        let prune_query = query.clone();
+        let prune: Prune = Box::new(move |entry: &DirEntry| prune_query.prunes(entry));
+        let options = &self.options;
+        let walks = self.roots.iter().map(|root| options.walk(root)).collect();
+        // Skipping the rest of a directory is only safe while each
+        // directory is yielded before its contents.
+        let limited = self.max_results_per_dir.is_some() || self.max_results_per_subtree.is_some();
+        let skip = match limited {
+            true if !options.contents_first => Some(Rc::new(Cell::new(None))),
+            _ => None,
+        };
+        TryIter {
+            it: Walk::new(
+                walks,
+                self.root_order,
+                prune,
+                skip.clone(),
+                self.options.contents_first,
+                self.options.min_depth,
+            ),
+            predicate: Box::new(move |entry: &DirEntry| query.matches(entry)),
+            seen: if self.dedup {
+                Some(HashSet::new())
+            } else {
+                None
+            },
+            policy: self.error_policy,
+            errors: Vec::new(),
+            max_visited: self.max_visited,
+            visited: 0,
+            max_results: self.max_results,
+            results: 0,
+            per_dir: if limited {
+                Some(DirLimits::new(
+                    self.max_results_per_dir,
+                    self.max_results_per_subtree,
+                    skip,
+                ))
+            } else {
+                None
+            },
+            done: self.options.is_empty(),
+            sort: self.sort_results,
+            unsorted: Vec::new(),
+            sorted: None,
+        }
+    }
+
+    /// Returns the first entry found, stopping the search there.
+    ///
+    /// Errors are handled as for [`Finder::into_iter`].
+    ///
+    /// ```no_run
+    /// use where_is::Finder;
+    ///
+    /// if let Some(entry) = Finder::new(".", "Cargo.toml").first() {
+    ///     println!("{}", entry.path().display());
+    /// }
+    /// ```
+    ///
+    /// [`Finder::into_iter`]: struct.Finder.html#method.into_iter
+    pub fn first(self) -> Option<DirEntry> {
+        self.max_results(1).into_iter().next()
+    }
+
+    /// Returns true if any entry matches, stopping the search as soon as
+    /// one is found.
+    pub fn exists(self) -> bool {
+        self.first().is_some()
+    }
+
+    /// Takes the conditions of the search, leaving a `Finder` which matches
+    /// anything in their place.
+    fn take_query(&mut self) -> Query {
+        let mut filters = std::mem::take(&mut self.filters);
+        let mut prunes = std::mem::take(&mut self.prunes);
+        // Checked ahead of other filters, which may need to read metadata.
+        if !self.types.is_empty() {
+            let types = self.types.iter().copied().map(Matcher::boxed);
+            filters.insert(0, any_of(types).boxed());
+        }
+        match self.hidden {
+            HiddenPolicy::Include => {}
+            HiddenPolicy::Exclude => filters.insert(0, Not(Hidden).boxed()),
+            HiddenPolicy::Only => filters.insert(0, Hidden.boxed()),
+        }
+        if self.skip_hidden_dirs {
+            prunes.push(Hidden.boxed());
+        }
+        Query {
+            pattern: std::mem::replace(&mut self.pattern, Pattern::any()),
+            filters,
+            prunes,
+        }
+    }
+
+    /// Converts the `Finder` into an iterator which reports, alongside each
+    /// entry, which target it matched.
+    ///
+    /// ```no_run
+    /// use where_is::Finder;
+    ///
+    /// let targets = ["Cargo.toml", "package.json", "go.mod"];
+    /// for m in Finder::with_targets(".", &targets).matches() {
+    ///     println!("{} project at {}", targets[m.target()], m.entry().path().display());
+    /// }
+    /// ```
+    pub fn matches(self) -> Matches<Walk, EntryFilter> {
+        let pattern = self.pattern.clone();
+        Matches {
+            it: self.into_iter(),
+            pattern,
+        }
+    }
+
+    /// Converts the `Finder` into an iterator over the regular files it
+    /// finds whose contents match `contents`, along with the lines which
+    /// matched.
+    ///
+    /// Files are only read once they satisfy every other condition of the
+    /// search. See [`Contents`] for an example.
+    ///
+    /// [`Contents`]: struct.Contents.html
+    pub fn search_contents(self, contents: Contents) -> ContentMatches<Walk, EntryFilter> {
+        ContentMatches {
+            it: self.matching(EntryType::File).into_iter(),
+            contents,
+        }
+    }
+
+    /// Converts the `Finder` into an iterator over the results of a
+    /// parallel search, which reads directories on several threads at once.
+    ///
+    /// Entries are matched on the threads which find them, so matchers may
+    /// run concurrently, and are yielded in no particular order unless
+    /// [`Finder::deterministic`] is set. Errors are handled as for
+    /// [`Finder::into_iter`].
+    ///
+    /// All options apply as they would to a serial search, except
+    /// [`Finder::root_order`], [`Finder::max_open`],
+    /// [`Finder::contents_first`] and [`Finder::breadth_first`], which have
+    /// no effect. Each thread holds at most one directory open.
+    ///
+    /// ```no_run
+    /// use where_is::Finder;
+    ///
+    /// let logs: Vec<_> = Finder::with_glob("/var/log", "*.log")?
+    ///     .threads(8)
+    ///     .par_iter()
+    ///     .collect();
+    /// # Ok::<(), where_is::Error>(())
+    /// ```
+    ///
+    /// [`Finder::deterministic`]: struct.Finder.html#method.deterministic
+    /// [`Finder::into_iter`]: struct.Finder.html#method.into_iter
+    /// [`Finder::root_order`]: struct.Finder.html#method.root_order
+    /// [`Finder::max_open`]: struct.Finder.html#method.max_open
+    /// [`Finder::contents_first`]: struct.Finder.html#method.contents_first
+    /// [`Finder::breadth_first`]: struct.Finder.html#method.breadth_first
+    pub fn par_iter(self) -> IteratorFilter<ParWalk, EntryFilter> {
+        IteratorFilter {
+            it: self.par_try_iter(),
+        }
+    }
+
+    /// Converts the `Finder` into a fallible iterator over the results of a
+    /// parallel search, which returns errors alongside matching entries
+    /// when using [`ErrorPolicy::Yield`].
+    ///
+    /// See [`Finder::par_iter`] for how parallel searches differ from
+    /// serial ones.
+    ///
+    /// [`ErrorPolicy::Yield`]: enum.ErrorPolicy.html#variant.Yield
+    /// [`Finder::par_iter`]: struct.Finder.html#method.par_iter
+    pub fn par_try_iter(self) -> TryIter<ParWalk, EntryFilter> {
+        let deterministic = self.deterministic;
+        let ordered = deterministic || self.sort_results.is_some();
+        let threads = self.thread_count();
+        self.parallel()
+            .searched(ordered, |search| search.spawn(threads, deterministic))
+    }
+
+    /// Runs a parallel search, calling `f` with each matching entry, and
+    /// returns the errors encountered.
+    ///
+    /// `f` is called on the threads of the search as entries are found,
+    /// unless [`Finder::deterministic`] or [`Finder::sort_results`] is set,
+    /// in which case it is called on the current thread, in order, once
+    /// the search is complete. Errors are
+    /// returned unless using [`ErrorPolicy::Skip`]. See
+    /// [`Finder::par_iter`] for how parallel searches differ from serial
+    /// ones.
+    ///
+    /// ```no_run
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use where_is::Finder;
+    ///
+    /// let total = AtomicU64::new(0);
+    /// Finder::with_glob(".", "*.rs")?.par_for_each(|entry| {
+    ///     if let Ok(md) = entry.metadata() {
+    ///         total.fetch_add(md.len(), Ordering::Relaxed);
+    ///     }
+    /// });
+    /// # Ok::<(), where_is::Error>(())
+    /// ```
+    ///
+    /// [`Finder::deterministic`]: struct.Finder.html#method.deterministic
+    /// [`Finder::sort_results`]: struct.Finder.html#method.sort_results
+    /// [`ErrorPolicy::Skip`]: enum.ErrorPolicy.html#variant.Skip
+    /// [`Finder::par_iter`]: struct.Finder.html#method.par_iter
+    pub fn par_for_each<F>(self, f: F) -> Vec<Error>
+    where
+        F: Fn(DirEntry) + Sync,
+    {
+        if self.deterministic || self.sort_results.is_some() {
+            let mut iter = self.par_iter();
+            iter.by_ref().for_each(f);
+            return iter.it.errors;
+        }
+        let policy = self.error_policy;
+        let threads = self.thread_count();
+        let mut finder = self.parallel();
+        let limits = finder.limits();
+        let query = finder.take_query();
+        let search = Search::new(finder.roots, query, finder.options, finder.dedup, limits);
+        let errors = Mutex::new(Vec::new());
+        search.run(threads, &|_, item| {
+            match item {
+                Ok(entry) => f(entry),
+                Err(_) if policy == ErrorPolicy::Skip => {}
+                Err(err) => errors.lock().unwrap_or_else(|e| e.into_inner()).push(err),
+            }
+            true
+        });
+        errors.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Starts a search which reads directories itself rather than through
+    /// walkdir, and so matches, counts and deduplicates entries itself.
+    ///
+    /// When results are `ordered` after the search, they are deduplicated
+    /// once ordered instead, so that the same one of several paths to a
+    /// file is always kept.
+    fn searched<I, F>(mut self, ordered: bool, start: F) -> TryIter<I, EntryFilter>
+    where
+        F: FnOnce(Search) -> I,
+    {
+        let dedup = self.dedup;
+        // Results are only counted by the search while it yields them in
+        // their final order.
+        let mut limits = self.limits();
+        if ordered {
+            limits.results = None;
+        }
+        let query = self.take_query();
+        let search = Search::new(self.roots, query, self.options, dedup && !ordered, limits);
+        TryIter {
+            it: start(search),
+            predicate: Box::new(|_: &DirEntry| Ok(true)),
+            seen: if ordered && dedup {
+                Some(HashSet::new())
+            } else {
+                None
+            },
+            policy: self.error_policy,
+            errors: Vec::new(),
+            max_visited: None,
+            visited: 0,
+            max_results: self.max_results,
+            results: 0,
+            per_dir: None,
+            done: false,
+            sort: self.sort_results,
+            unsorted: Vec::new(),
+            sorted: None,
+        }
+    }
+
+    fn limits(&self) -> Limits {
+        Limits {
+            visited: self.max_visited,
+            results: self.max_results,
+            per_dir: self.max_results_per_dir,
+            per_subtree: self.max_results_per_subtree,
+        }
+    }
+
+    /// Clears the options which only apply to serial searches.
+    fn parallel(mut self) -> Self {
+        self.options.breadth_first = false;
+        self.options.stop_at_shallowest = false;
+        self
+    }
+
+    fn thread_count(&self) -> usize {
+        match self.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
         }
     }
 }
 
+/// The conditions an entry must satisfy to be yielded, and under which a
+/// directory is pruned, shared by the serial and parallel searches.
+struct Query {
+    pattern: Pattern,
+    filters: Vec<Box<dyn Matcher>>,
+    prunes: Vec<Box<dyn Matcher>>,
+}
+
+impl Query {
+    /// Returns true if `entry` should be yielded.
+    fn matches(&self, entry: &DirEntry) -> Result<bool> {
+        if !self.pattern.matches(entry) {
+            return Ok(false);
+        }
+        for filter in &self.filters {
+            if !filter.is_match(entry)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns true if the contents of the directory `entry` should be
+    /// skipped.
+    fn prunes(&self, entry: &DirEntry) -> Result<bool> {
+        if !self.pattern.may_match_below(entry) {
+            return Ok(true);
+        }
+        for prune in &self.prunes {
+            if prune.is_match(entry)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// A fallible predicate deciding whether an entry is yielded.
+pub type EntryFilter = Box<dyn FnMut(&DirEntry) -> Result<bool>>;
+
 impl IntoIterator for Finder {
     type Item = DirEntry;
-    type IntoIter = IteratorFilter<walkdir::IntoIter, Box<dyn FnMut(&DirEntry) -> bool>>;
+    type IntoIter = IteratorFilter<Walk, EntryFilter>;
 
     fn into_iter(self) -> Self::IntoIter {
-        let target = self.target;
         IteratorFilter {
-            it: self.walker.into_iter(),
-            predicate: Box::new(move |entry: &DirEntry| -> bool {
-                match entry.path().file_name() {
-                    Some(name) => name.to_string_lossy() == target,
-                    None => false,
-                }
-            }),
+            it: self.try_iter(),
         }
     }
 }
 
 /// An iterator for recursively finding all instances of a file
 /// within a directory hierarchy.
+///
+/// Errors are handled according to the [`ErrorPolicy`] of the [`Finder`]
+/// which created this iterator.
+///
+/// [`ErrorPolicy`]: enum.ErrorPolicy.html
+/// [`Finder`]: struct.Finder.html
 pub struct IteratorFilter<I, P> {
-    it: I,
-    predicate: P,
+    it: TryIter<I, P>,
+}
+
+impl<I, P> IteratorFilter<I, P> {
+    /// Returns the errors recorded so far.
+    ///
+    /// This is always empty when using [`ErrorPolicy::Skip`].
+    ///
+    /// [`ErrorPolicy::Skip`]: enum.ErrorPolicy.html#variant.Skip
+    pub fn errors(&self) -> &[Error] {
+        self.it.errors()
+    }
 }
 
 impl<I, P> Iterator for IteratorFilter<I, P>
 where
     I: Iterator<Item = Result<DirEntry>>,
-    P: FnMut(&DirEntry) -> bool,
+    P: FnMut(&DirEntry) -> Result<bool>,
 {
     type Item = DirEntry;
 
     fn next(&mut self) -> Option<DirEntry> {
         loop {
-            let dent = self.it.next()?.ok()?;
-            if !(self.predicate)(&dent) {
-                continue;
+            match self.it.next()? {
+                Ok(dent) => return Some(dent),
+                Err(err) => self.it.errors.push(err),
             }
-            return Some(dent);
         }
     }
 }
 
+/// An entry found by a search, along with the target it matched.
+#[derive(Debug)]
+pub struct Match {
+    entry: DirEntry,
+    target: usize,
+}
+
+impl Match {
+    /// Returns the entry which matched.
+    pub fn entry(&self) -> &DirEntry {
+        &self.entry
+    }
+
+    /// Returns the position of the matched target, within the targets
+    /// provided to [`Finder::with_targets`] or [`Pattern::names`].
+    ///
+    /// For patterns with a single target, this is always zero.
+    ///
+    /// [`Finder::with_targets`]: struct.Finder.html#method.with_targets
+    /// [`Pattern::names`]: struct.Pattern.html#method.names
+    pub fn target(&self) -> usize {
+        self.target
+    }
+
+    /// Converts the match into the entry which matched.
+    pub fn into_entry(self) -> DirEntry {
+        self.entry
+    }
+}
+
+/// An iterator over the [`Match`]es of a search.
+///
+/// Created by [`Finder::matches`].
+///
+/// [`Match`]: struct.Match.html
+/// [`Finder::matches`]: struct.Finder.html#method.matches
+pub struct Matches<I, P> {
+    it: IteratorFilter<I, P>,
+    pattern: Pattern,
+}
+
+impl<I, P> Matches<I, P> {
+    /// Returns the errors recorded so far.
+    ///
+    /// This is always empty when using [`ErrorPolicy::Skip`].
+    ///
+    /// [`ErrorPolicy::Skip`]: enum.ErrorPolicy.html#variant.Skip
+    pub fn errors(&self) -> &[Error] {
+        self.it.errors()
+    }
+}
+
+impl<I, P> Iterator for Matches<I, P>
+where
+    I: Iterator<Item = Result<DirEntry>>,
+    P: FnMut(&DirEntry) -> Result<bool>,
+{
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        let entry = self.it.next()?;
+        let target = self.pattern.matched_target(&entry).unwrap_or(0);
+        Some(Match { entry, target })
+    }
+}
+
+/// A file found by a search, along with the lines which matched a
+/// [`Contents`] search.
+///
+/// [`Contents`]: struct.Contents.html
+#[derive(Debug)]
+pub struct ContentMatch {
+    entry: DirEntry,
+    lines: Vec<LineMatch>,
+}
+
+impl ContentMatch {
+    /// Returns the file which matched.
+    pub fn entry(&self) -> &DirEntry {
+        &self.entry
+    }
+
+    /// Returns the matching lines of the file, in order. There is always
+    /// at least one.
+    pub fn lines(&self) -> &[LineMatch] {
+        &self.lines
+    }
+
+    /// Consumes the match, returning the file and its matching lines.
+    pub fn into_parts(self) -> (DirEntry, Vec<LineMatch>) {
+        (self.entry, self.lines)
+    }
+}
+
+/// An iterator over the files found by a search whose contents match, and
+/// the lines which matched.
+///
+/// Created by [`Finder::search_contents`]. Errors reading a file are
+/// handled like other errors of the search, although they are never
+/// yielded.
+///
+/// [`Finder::search_contents`]: struct.Finder.html#method.search_contents
+pub struct ContentMatches<I, P> {
+    it: IteratorFilter<I, P>,
+    contents: Contents,
+}
+
+impl<I, P> ContentMatches<I, P> {
+    /// Returns the errors recorded so far.
+    ///
+    /// This is always empty when using [`ErrorPolicy::Skip`].
+    ///
+    /// [`ErrorPolicy::Skip`]: enum.ErrorPolicy.html#variant.Skip
+    pub fn errors(&self) -> &[Error] {
+        self.it.errors()
+    }
+}
+
+impl<I, P> Iterator for ContentMatches<I, P>
+where
+    I: Iterator<Item = Result<DirEntry>>,
+    P: FnMut(&DirEntry) -> Result<bool>,
+{
+    type Item = ContentMatch;
+
+    fn next(&mut self) -> Option<ContentMatch> {
+        loop {
+            let entry = self.it.next()?;
+            match self.contents.search(entry.path()) {
+                Ok(lines) if lines.is_empty() => {}
+                Ok(lines) => return Some(ContentMatch { entry, lines }),
+                Err(err) => {
+                    let err = matcher::io_error(&entry, err);
+                    if let Some(err) = self.it.it.handle_error(err) {
+                        self.it.it.errors.push(err);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A fallible iterator for recursively finding all instances of a file
+/// within a directory hierarchy.
+///
+/// Created by [`Finder::try_iter`].
+///
+/// [`Finder::try_iter`]: struct.Finder.html#method.try_iter
+pub struct TryIter<I, P> {
+    it: I,
+    predicate: P,
+    seen: Option<HashSet<FileId>>,
+    policy: ErrorPolicy,
+    errors: Vec<Error>,
+    max_visited: Option<usize>,
+    visited: usize,
+    max_results: Option<usize>,
+    results: usize,
+    per_dir: Option<DirLimits>,
+    done: bool,
+    sort: Option<SortKey>,
+    // While sorting, the results found so far, then the sorted results.
+    unsorted: Vec<DirEntry>,
+    sorted: Option<vec::IntoIter<DirEntry>>,
+}
+
+impl<I, P> TryIter<I, P> {
+    /// Returns the errors recorded so far.
+    ///
+    /// This is only non-empty when using [`ErrorPolicy::Collect`].
+    ///
+    /// [`ErrorPolicy::Collect`]: enum.ErrorPolicy.html#variant.Collect
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// Applies the error policy to `err`, returning it if it should be
+    /// passed on to the caller.
+    fn handle_error(&mut self, err: Error) -> Option<Error> {
+        match self.policy {
+            ErrorPolicy::Skip => None,
+            ErrorPolicy::Collect => {
+                self.errors.push(err);
+                None
+            }
+            ErrorPolicy::Yield => Some(err),
+        }
+    }
+}
+
+impl<I, P> Iterator for TryIter<I, P>
+where
+    I: Iterator<Item = Result<DirEntry>>,
+    P: FnMut(&DirEntry) -> Result<bool>,
+{
+    type Item = Result<DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.max_results.is_some_and(|n| self.results >= n) {
+            return None;
+        }
+        let result = self.next_result()?;
+        self.results += usize::from(result.is_ok());
+        Some(result)
+    }
+}
+
+impl<I, P> TryIter<I, P>
+where
+    I: Iterator<Item = Result<DirEntry>>,
+    P: FnMut(&DirEntry) -> Result<bool>,
+{
+    /// Returns the next result of the search, in sorted order if sorting.
+    fn next_result(&mut self) -> Option<Result<DirEntry>> {
+        let key = match self.sort {
+            Some(key) => key,
+            None => return self.next_found(),
+        };
+        if self.sorted.is_none() {
+            while let Some(result) = self.next_found() {
+                match result {
+                    Ok(dent) => self.unsorted.push(dent),
+                    Err(err) => return Some(Err(err)),
+                }
+            }
+            let found = std::mem::take(&mut self.unsorted);
+            self.sorted = Some(sort::sort_entries(found, key).into_iter());
+        }
+        let (sorted, seen) = (self.sorted.as_mut()?, &mut self.seen);
+        sorted
+            .find(|dent| match seen {
+                Some(seen) => seen.insert(FileId::of(dent.path())),
+                None => true,
+            })
+            .map(Ok)
+    }
+
+    /// Returns the next result of the search, in the order it was found.
+    fn next_found(&mut self) -> Option<Result<DirEntry>> {
+        while !self.done {
+            let err = match self.it.next()? {
+                Ok(dent) => {
+                    if Some(self.visited) == self.max_visited {
+                        self.done = true;
+                        Error::BudgetExceeded {
+                            limit: self.visited,
+                            depth: dent.depth(),
+                            path: dent.into_path(),
+                        }
+                    } else {
+                        self.visited += 1;
+                        match (self.predicate)(&dent) {
+                            Ok(true) => {}
+                            Ok(false) => continue,
+                            Err(err) => {
+                                if let Some(err) = self.handle_error(err) {
+                                    return Some(Err(err));
+                                }
+                                continue;
+                            }
+                        }
+                        // Sorted results are deduplicated once sorted.
+                        if let (Some(seen), None) = (&mut self.seen, self.sort) {
+                            if !seen.insert(FileId::of(dent.path())) {
+                                continue;
+                            }
+                        }
+                        if self.per_dir.as_mut().is_some_and(|l| !l.admit(&dent)) {
+                            continue;
+                        }
+                        return Some(Ok(dent));
+                    }
+                }
+                Err(err) => err,
+            };
+            if let Some(err) = self.handle_error(err) {
+                return Some(Err(err));
+            }
+        }
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::entry::dir_entry;
     use tempdir::TempDir;
 
     #[test]
@@ -122,4 +1307,1132 @@ mod tests {
         assert_eq!(tmp_dir.path().join("a/b/c/a"), iter.next().unwrap().path());
         assert!(iter.next().is_none());
     }
+
+    #[test]
+    fn find_regex_file_name() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("logs")).unwrap();
+        for name in &["build_1.log", "build_22.log", "build_x.log", "test_3.log"] {
+            std::fs::write(tmp_dir.path().join("logs").join(name), "").unwrap();
+        }
+
+        let finder = Finder::with_regex(tmp_dir.path(), r"^build_\d+\.log$").unwrap();
+        let mut found: Vec<_> = finder.into_iter().map(|e| e.into_path()).collect();
+        found.sort();
+
+        assert_eq!(
+            found,
+            vec![
+                tmp_dir.path().join("logs/build_1.log"),
+                tmp_dir.path().join("logs/build_22.log"),
+            ]
+        );
+    }
+
+    #[test]
+    fn find_regex_relative_path() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("a/b")).unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("c/b")).unwrap();
+
+        let pattern = Pattern::regex("^a/b$")
+            .unwrap()
+            .match_on(MatchOn::RelativePath);
+        let finder = Finder::with_pattern(tmp_dir.path(), pattern);
+        let mut iter = finder.into_iter();
+
+        assert_eq!(tmp_dir.path().join("a/b"), iter.next().unwrap().path());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn invalid_regex() {
+        assert!(Finder::with_regex(".", "(unclosed").is_err());
+    }
+
+    #[test]
+    fn find_glob_file_name() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("a/b")).unwrap();
+        for name in &[
+            "a/foo.toml",
+            "a/b/bar.toml",
+            "baz.toml",
+            "test_1.txt",
+            "test_10.txt",
+        ] {
+            std::fs::write(tmp_dir.path().join(name), "").unwrap();
+        }
+
+        let find = |glob: &str| -> Vec<_> {
+            let mut found: Vec<_> = Finder::with_glob(tmp_dir.path(), glob)
+                .unwrap()
+                .into_iter()
+                .map(|e| e.path().strip_prefix(tmp_dir.path()).unwrap().to_path_buf())
+                .collect();
+            found.sort();
+            found
+        };
+
+        assert_eq!(
+            find("{foo,bar}.toml"),
+            vec![Path::new("a/b/bar.toml"), Path::new("a/foo.toml")]
+        );
+        assert_eq!(find("test_?.txt"), vec![Path::new("test_1.txt")]);
+        assert_eq!(find("[!a-z]*"), Vec::<&Path>::new());
+        assert_eq!(find("a/*.toml"), vec![Path::new("a/foo.toml")]);
+        assert_eq!(
+            find("a/**/*.toml"),
+            vec![Path::new("a/b/bar.toml"), Path::new("a/foo.toml")]
+        );
+    }
+
+    #[test]
+    fn glob_prunes_diverging_directories() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("src/x")).unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("other/src/x")).unwrap();
+        std::fs::write(tmp_dir.path().join("src/x/mod.rs"), "").unwrap();
+        std::fs::write(tmp_dir.path().join("other/src/x/mod.rs"), "").unwrap();
+
+        let pattern = Pattern::glob("src/**/mod.rs").unwrap();
+        let root = tmp_dir.path().to_path_buf();
+        assert!(pattern.may_match_below(&dir_entry(&root, "src/x")));
+        assert!(!pattern.may_match_below(&dir_entry(&root, "other")));
+
+        let finder = Finder::with_pattern(tmp_dir.path(), pattern);
+        let mut iter = finder.into_iter();
+        assert_eq!(
+            tmp_dir.path().join("src/x/mod.rs"),
+            iter.next().unwrap().path()
+        );
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn invalid_glob() {
+        assert!(Finder::with_glob(".", "a[").is_err());
+    }
+
+    #[test]
+    fn case_fold_and_normalization() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path().to_path_buf();
+        std::fs::create_dir_all(root.join("Src/Docs")).unwrap();
+        std::fs::write(root.join("Src/Docs/README.md"), "").unwrap();
+        // "café" with a decomposed accent, as macOS would store it.
+        std::fs::write(root.join("Src/cafe\u{301}.txt"), "").unwrap();
+        std::fs::write(root.join("Src/stra\u{df}e.txt"), "").unwrap();
+        // "Kelvin", starting with the Kelvin sign rather than a `K`.
+        std::fs::write(root.join("Src/\u{212a}elvin"), "").unwrap();
+        let readme = dir_entry(&root, "Src/Docs/README.md");
+        let cafe = dir_entry(&root, "Src/cafe\u{301}.txt");
+
+        let exact = |target| Pattern::exact(target).case_fold(CaseFold::Ascii);
+        assert!(exact("readme.md").matches(&readme));
+        assert!(!Pattern::exact("readme.md").matches(&readme));
+        assert!(!exact("CAF\u{c9}.txt").matches(&cafe));
+        let unicode = Pattern::names(["x", "CAF\u{c9}.TXT"])
+            .case_fold(CaseFold::Unicode)
+            .normalize(Normalization::Nfc);
+        assert_eq!(unicode.matched_target(&cafe), Some(1));
+        let street = Pattern::exact("STRASSE.txt").case_fold(CaseFold::Unicode);
+        assert!(street.matches(&dir_entry(&root, "Src/stra\u{df}e.txt")));
+        let regex = Pattern::regex("^caf\u{e9}").unwrap();
+        assert!(!regex.matches(&cafe));
+        assert!(regex.normalize(Normalization::Nfc).matches(&cafe));
+
+        // Regular expressions and globs fold case just as other patterns do:
+        // only ASCII letters for `Ascii`, and in full for `Unicode`.
+        let street = dir_entry(&root, "Src/stra\u{df}e.txt");
+        let regex = |re| Pattern::regex(re).unwrap();
+        assert!(regex("^STRA[S\u{df}]E")
+            .case_fold(CaseFold::Ascii)
+            .matches(&street));
+        assert!(!regex("^STRA\u{1e9e}E")
+            .case_fold(CaseFold::Ascii)
+            .matches(&street));
+        assert!(regex("^STRA\u{1e9e}E")
+            .case_fold(CaseFold::Unicode)
+            .matches(&street));
+        assert!(regex("^(?:STRASSE)+\\.")
+            .case_fold(CaseFold::Unicode)
+            .matches(&street));
+        let kelvin = dir_entry(&root, "Src/\u{212a}elvin");
+        assert!(!regex("^k").case_fold(CaseFold::Ascii).matches(&kelvin));
+        assert!(!regex("^[a-z]").case_fold(CaseFold::Ascii).matches(&kelvin));
+        assert!(regex("^[a-z]")
+            .case_fold(CaseFold::Unicode)
+            .matches(&kelvin));
+        assert!(regex("^[a-z]").case_fold(CaseFold::Ascii).matches(&readme));
+        let glob = |glob| Pattern::glob(glob).unwrap();
+        assert!(glob("STRASSE.*")
+            .case_fold(CaseFold::Unicode)
+            .matches(&street));
+        assert!(!glob("STRASSE.*")
+            .case_fold(CaseFold::Ascii)
+            .matches(&street));
+        assert!(!glob("k*").case_fold(CaseFold::Ascii).matches(&kelvin));
+
+        // Case folding also applies when pruning with a path glob.
+        let glob = Pattern::glob("src/docs/*.md")
+            .unwrap()
+            .case_fold(CaseFold::Ascii);
+        assert!(glob.may_match_below(&dir_entry(&root, "Src")));
+        assert_eq!(
+            sorted_paths(Finder::with_pattern(&root, glob)),
+            vec![readme.into_path()]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path();
+        // "café.txt" and "cafï.txt" in Latin-1, which are not valid UTF-8
+        // and would be equal after lossy conversion.
+        let e_acute = OsStr::from_bytes(b"caf\xe9.txt");
+        let i_diaeresis = OsStr::from_bytes(b"caf\xef.txt");
+        std::fs::write(root.join(e_acute), "").unwrap();
+        std::fs::write(root.join(i_diaeresis), "").unwrap();
+        std::fs::write(root.join("cafe.txt"), "").unwrap();
+        let find = |pattern| sorted_paths(Finder::with_pattern(root, pattern));
+
+        assert_eq!(find(Pattern::exact(e_acute)), vec![root.join(e_acute)]);
+        assert_eq!(
+            find(Pattern::exact(OsStr::from_bytes(b"CAF\xe9.TXT")).case_fold(CaseFold::Unicode)),
+            vec![root.join(e_acute)]
+        );
+        assert_eq!(
+            find(Pattern::regex(r"^caf(?-u:\xEF)\.").unwrap()),
+            vec![root.join(i_diaeresis)]
+        );
+        assert_eq!(
+            find(Pattern::glob("caf?.txt").unwrap()),
+            vec![
+                root.join("cafe.txt"),
+                root.join(e_acute),
+                root.join(i_diaeresis)
+            ]
+        );
+    }
+
+    /// Creates a tree with readable directories `a` and `c` and a
+    /// dangling symlink `b`, returning a finder for `target` which reports
+    /// an error when visiting `b`.
+    #[cfg(unix)]
+    fn tree_with_error(tmp_dir: &TempDir, target: &str) -> Finder {
+        std::fs::create_dir_all(tmp_dir.path().join("a/x")).unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("c/x")).unwrap();
+        std::os::unix::fs::symlink(tmp_dir.path().join("missing"), tmp_dir.path().join("b"))
+            .unwrap();
+        Finder::new(tmp_dir.path(), target).follow_links(true)
+    }
+
+    fn sorted_paths<I: IntoIterator<Item = DirEntry>>(entries: I) -> Vec<PathBuf> {
+        let mut paths: Vec<_> = entries.into_iter().map(DirEntry::into_path).collect();
+        paths.sort();
+        paths
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skip_errors() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let mut iter = tree_with_error(&tmp_dir, "x").into_iter();
+
+        assert_eq!(
+            sorted_paths(&mut iter),
+            vec![tmp_dir.path().join("a/x"), tmp_dir.path().join("c/x")]
+        );
+        assert!(iter.errors().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn collect_errors() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let finder = tree_with_error(&tmp_dir, "x").error_policy(ErrorPolicy::Collect);
+        let mut iter = finder.into_iter();
+
+        assert_eq!(
+            sorted_paths(&mut iter),
+            vec![tmp_dir.path().join("a/x"), tmp_dir.path().join("c/x")]
+        );
+        assert_eq!(iter.errors().len(), 1);
+        assert_eq!(
+            iter.errors()[0].path(),
+            Some(tmp_dir.path().join("b").as_path())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn yield_errors() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let finder = tree_with_error(&tmp_dir, "x").error_policy(ErrorPolicy::Yield);
+        let (found, errors): (Vec<_>, Vec<_>) = finder.try_iter().partition(|r| r.is_ok());
+
+        assert_eq!(
+            sorted_paths(found.into_iter().map(|r| r.unwrap())),
+            vec![tmp_dir.path().join("a/x"), tmp_dir.path().join("c/x")]
+        );
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn visit_budget() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("a/b/c")).unwrap();
+
+        let finder = Finder::new(tmp_dir.path(), "c")
+            .max_visited(2)
+            .error_policy(ErrorPolicy::Yield);
+        let mut iter = finder.try_iter();
+
+        match iter.next().unwrap() {
+            Err(Error::BudgetExceeded { limit, path, depth }) => {
+                assert_eq!(limit, 2);
+                assert_eq!(path, tmp_dir.path().join("a/b"));
+                assert_eq!(depth, 2);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn depth_bounds() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("a/b/a/b/a")).unwrap();
+        let find = |min: usize, max: usize| {
+            sorted_paths(
+                Finder::new(tmp_dir.path(), "a")
+                    .min_depth(min)
+                    .max_depth(max),
+            )
+        };
+
+        assert_eq!(
+            find(0, 3),
+            vec![tmp_dir.path().join("a"), tmp_dir.path().join("a/b/a")]
+        );
+        assert_eq!(
+            find(2, 5),
+            vec![
+                tmp_dir.path().join("a/b/a"),
+                tmp_dir.path().join("a/b/a/b/a")
+            ]
+        );
+        assert!(find(4, 3).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follow_links() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("a/target")).unwrap();
+        std::os::unix::fs::symlink(tmp_dir.path().join("a"), tmp_dir.path().join("link")).unwrap();
+
+        assert_eq!(
+            sorted_paths(Finder::new(tmp_dir.path(), "target")),
+            vec![tmp_dir.path().join("a/target")]
+        );
+        assert_eq!(
+            sorted_paths(Finder::new(tmp_dir.path(), "target").follow_links(true)),
+            vec![
+                tmp_dir.path().join("a/target"),
+                tmp_dir.path().join("link/target")
+            ]
+        );
+    }
+
+    #[test]
+    fn multiple_roots() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        for dir in &["usr/lib/a", "usr/local/lib/a", "usr/local/lib/b/a"] {
+            std::fs::create_dir_all(tmp_dir.path().join(dir)).unwrap();
+        }
+        let finder = || {
+            Finder::new(tmp_dir.path().join("usr/lib"), "a")
+                .add_root(tmp_dir.path().join("usr/local/lib"))
+        };
+
+        let sequential: Vec<_> = finder().into_iter().map(DirEntry::into_path).collect();
+        assert_eq!(sequential[0], tmp_dir.path().join("usr/lib/a"));
+        assert_eq!(sequential.len(), 3);
+
+        let interleaved: Vec<_> = finder()
+            .root_order(RootOrder::Interleaved)
+            .max_depth(1)
+            .into_iter()
+            .map(DirEntry::into_path)
+            .collect();
+        assert_eq!(
+            interleaved,
+            vec![
+                tmp_dir.path().join("usr/lib/a"),
+                tmp_dir.path().join("usr/local/lib/a")
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dedup_overlapping_roots() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("a/b/target")).unwrap();
+        std::os::unix::fs::symlink(tmp_dir.path().join("a/b"), tmp_dir.path().join("link"))
+            .unwrap();
+        let finder = || {
+            Finder::new(tmp_dir.path().join("a"), "target")
+                .add_root(tmp_dir.path().join("a/b"))
+                .add_root(tmp_dir.path().join("link"))
+        };
+
+        assert_eq!(finder().into_iter().count(), 3);
+        assert_eq!(
+            sorted_paths(finder().dedup(true)),
+            vec![tmp_dir.path().join("a/b/target")]
+        );
+    }
+
+    #[test]
+    fn multiple_targets() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("rust")).unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("web/node_modules/dep")).unwrap();
+        for file in &[
+            "rust/Cargo.toml",
+            "web/package.json",
+            "web/node_modules/dep/package.json",
+            "web/README.md",
+        ] {
+            std::fs::write(tmp_dir.path().join(file), "").unwrap();
+        }
+
+        let targets = ["Cargo.toml", "package.json", "go.mod"];
+        let mut found: Vec<_> = Finder::with_targets(tmp_dir.path(), targets)
+            .matches()
+            .map(|m| (m.target(), m.into_entry().into_path()))
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                (0, tmp_dir.path().join("rust/Cargo.toml")),
+                (1, tmp_dir.path().join("web/node_modules/dep/package.json")),
+                (1, tmp_dir.path().join("web/package.json")),
+            ]
+        );
+    }
+
+    #[test]
+    fn find_with_matchers() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("src/target")).unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("target/debug")).unwrap();
+        std::fs::write(tmp_dir.path().join("src/target/huge.log"), vec![0; 64]).unwrap();
+        std::fs::write(tmp_dir.path().join("target/debug/huge.log"), vec![0; 64]).unwrap();
+        std::fs::write(tmp_dir.path().join("tiny.log"), "").unwrap();
+
+        let finder = Finder::with_glob(tmp_dir.path(), "*.log")
+            .unwrap()
+            .matching(Size::larger_than(32))
+            .matching(Under::new("target").not());
+        assert_eq!(
+            sorted_paths(finder),
+            vec![tmp_dir.path().join("src/target/huge.log")]
+        );
+
+        let shared = std::sync::Arc::new(EntryType::Dir.and(Pattern::exact("target")));
+        assert_eq!(
+            sorted_paths(Finder::with_matcher(tmp_dir.path(), shared.clone())),
+            vec![
+                tmp_dir.path().join("src/target"),
+                tmp_dir.path().join("target")
+            ]
+        );
+        assert_eq!(
+            sorted_paths(Finder::with_matcher(tmp_dir.path().join("src"), shared)),
+            vec![tmp_dir.path().join("src/target")]
+        );
+    }
+
+    #[test]
+    fn prune_directories() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        for dir in &[
+            "node_modules/dep",
+            "src/node_modules/dep",
+            "src/lib",
+            ".git/dep",
+        ] {
+            std::fs::create_dir_all(tmp_dir.path().join(dir)).unwrap();
+        }
+        let finder = || {
+            Finder::with_matcher(tmp_dir.path(), EntryType::Dir)
+                .min_depth(1)
+                .prune(Pattern::names(["node_modules", ".git"]))
+        };
+
+        assert_eq!(
+            sorted_paths(finder()),
+            vec![
+                tmp_dir.path().join(".git"),
+                tmp_dir.path().join("node_modules"),
+                tmp_dir.path().join("src"),
+                tmp_dir.path().join("src/lib"),
+                tmp_dir.path().join("src/node_modules"),
+            ]
+        );
+        // Pruned directories are never read.
+        let mut iter = finder()
+            .max_visited(6)
+            .error_policy(ErrorPolicy::Collect)
+            .into_iter();
+        assert_eq!(iter.by_ref().count(), 5);
+        assert!(iter.errors().is_empty());
+    }
+
+    #[test]
+    fn prune_above_min_depth() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path().join("root");
+        for dir in &["node_modules/x", "src/x"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::File::create(root.join(dir).join("f")).unwrap();
+        }
+        let modes: [fn(Finder) -> Vec<PathBuf>; 4] = [
+            |finder| sorted_paths(finder),
+            |finder| sorted_paths(finder.contents_first(true)),
+            |finder| sorted_paths(finder.breadth_first(true)),
+            |finder| sorted_paths(finder.threads(2).par_iter()),
+        ];
+
+        for find in modes {
+            let finder = || Finder::new(&root, "f").min_depth(2);
+            assert_eq!(
+                find(finder().prune(Pattern::exact("node_modules"))),
+                vec![root.join("src/x/f")]
+            );
+            // The root itself may be pruned, however deep the minimum.
+            assert!(find(finder().prune(Pattern::exact("root"))).is_empty());
+        }
+    }
+
+    #[test]
+    fn prune_contents_first() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        for dir in &["a", "b", "c"] {
+            std::fs::create_dir_all(tmp_dir.path().join(dir)).unwrap();
+            std::fs::File::create(tmp_dir.path().join(dir).join("x")).unwrap();
+        }
+        let glob = Finder::with_glob(tmp_dir.path(), "b/*")
+            .unwrap()
+            .contents_first(true);
+        assert_eq!(sorted_paths(glob), vec![tmp_dir.path().join("b/x")]);
+
+        let pruned = Finder::new(tmp_dir.path(), "x")
+            .prune(Pattern::exact("a"))
+            .contents_first(true);
+        assert_eq!(
+            sorted_paths(pruned),
+            vec![tmp_dir.path().join("b/x"), tmp_dir.path().join("c/x")]
+        );
+    }
+
+    #[test]
+    fn ignore_files() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let repo = tmp_dir.path();
+        std::fs::create_dir_all(repo.join(".git/info")).unwrap();
+        std::fs::create_dir_all(repo.join("build")).unwrap();
+        std::fs::create_dir_all(repo.join("sub")).unwrap();
+        for (name, contents) in &[
+            (".git/info/exclude", "secret.txt\n"),
+            (".gitignore", "build/\n/top.txt\n*.tmp.txt\n!keep.tmp.txt\n"),
+            ("sub/.ignore", "*.md\n"),
+            ("sub/.whereisignore", "!notes.md\n"),
+        ] {
+            std::fs::write(repo.join(name), contents).unwrap();
+        }
+        for name in &[
+            "top.txt",
+            "secret.txt",
+            "build/a.txt",
+            "x.tmp.txt",
+            "keep.tmp.txt",
+            "readme.md",
+            "sub/top.txt",
+            "sub/build",
+            "sub/x.tmp.txt",
+            "sub/readme.md",
+            "sub/notes.md",
+        ] {
+            std::fs::write(repo.join(name), "").unwrap();
+        }
+        let find = |root: &Path| {
+            sorted_paths(Finder::with_matcher(root, EntryType::File).ignore_files(true))
+        };
+
+        assert_eq!(
+            find(repo),
+            vec![
+                repo.join(".gitignore"),
+                repo.join("keep.tmp.txt"),
+                repo.join("readme.md"),
+                repo.join("sub/.ignore"),
+                repo.join("sub/.whereisignore"),
+                repo.join("sub/build"),
+                repo.join("sub/notes.md"),
+                repo.join("sub/top.txt"),
+            ]
+        );
+        // Rules from above the root still apply within the repository.
+        assert_eq!(
+            find(&repo.join("sub")),
+            vec![
+                repo.join("sub/.ignore"),
+                repo.join("sub/.whereisignore"),
+                repo.join("sub/build"),
+                repo.join("sub/notes.md"),
+                repo.join("sub/top.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn hidden_policy() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path();
+        std::fs::create_dir_all(root.join(".cache/a")).unwrap();
+        std::fs::create_dir_all(root.join("src/.b")).unwrap();
+        let find = |policy, skip| {
+            sorted_paths(
+                Finder::with_matcher(root, EntryType::Dir)
+                    .min_depth(1)
+                    .hidden(policy)
+                    .skip_hidden_dirs(skip),
+            )
+        };
+
+        assert_eq!(
+            find(HiddenPolicy::Exclude, false),
+            vec![root.join(".cache/a"), root.join("src")]
+        );
+        assert_eq!(
+            find(HiddenPolicy::Only, false),
+            vec![root.join(".cache"), root.join("src/.b")]
+        );
+        assert_eq!(find(HiddenPolicy::Exclude, true), vec![root.join("src")]);
+        assert_eq!(
+            find(HiddenPolicy::Include, true),
+            vec![root.join(".cache"), root.join("src"), root.join("src/.b")]
+        );
+        // The root is never hidden.
+        assert_eq!(
+            sorted_paths(
+                Finder::with_matcher(root.join(".cache"), EntryType::Dir)
+                    .hidden(HiddenPolicy::Exclude)
+            ),
+            vec![root.join(".cache"), root.join(".cache/a")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn entry_types() {
+        use std::os::unix::net::UnixListener;
+
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path();
+        std::fs::create_dir_all(root.join("tests")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/tests"), "").unwrap();
+        std::os::unix::fs::symlink(root.join("tests"), root.join("link")).unwrap();
+        std::os::unix::fs::symlink(root.join("missing"), root.join("broken")).unwrap();
+        let _socket = UnixListener::bind(root.join("socket")).unwrap();
+        let find = |finder: Finder| sorted_paths(finder.min_depth(1));
+
+        assert_eq!(
+            find(Finder::new(root, "tests").entry_type(EntryType::Dir)),
+            vec![root.join("tests")]
+        );
+        assert_eq!(
+            find(Finder::with_matcher(root, EntryType::Symlink)),
+            vec![root.join("broken"), root.join("link")]
+        );
+        assert_eq!(
+            find(
+                Finder::with_matcher(root, EntryType::Dir.target())
+                    .entry_type(EntryType::Symlink)
+                    .entry_type(EntryType::Socket)
+            ),
+            vec![root.join("link")]
+        );
+        assert_eq!(
+            find(Finder::with_matcher(root, EntryType::Symlink.target())),
+            vec![root.join("broken")]
+        );
+        assert_eq!(
+            find(Finder::with_matcher(root, EntryType::Socket)),
+            vec![root.join("socket")]
+        );
+    }
+
+    #[test]
+    fn search_contents() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path();
+        std::fs::create_dir_all(root.join("member")).unwrap();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"member\"]\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("member/Cargo.toml"), "[package]\n").unwrap();
+        std::fs::write(root.join("notes.txt"), "[workspace]\n").unwrap();
+        let finder = || Finder::with_glob(root, "*.toml").unwrap();
+
+        let found: Vec<_> = finder()
+            .search_contents(Contents::literal("[workspace]"))
+            .collect();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].entry().path(), root.join("Cargo.toml"));
+        assert_eq!(found[0].lines()[0].line_number(), 1);
+        assert_eq!(
+            sorted_paths(finder().matching(Contents::literal("[package]"))),
+            vec![root.join("member/Cargo.toml")]
+        );
+    }
+
+    #[test]
+    fn parallel_search() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path();
+        for i in 0..8 {
+            for dir in &["src/x", "target/debug", ".cache"] {
+                let dir = root.join(format!("crate{}", i)).join(dir);
+                std::fs::create_dir_all(&dir).unwrap();
+                std::fs::write(dir.join("mod.rs"), "").unwrap();
+                std::fs::write(dir.join("lib.txt"), "").unwrap();
+            }
+        }
+        std::fs::write(root.join("crate0/.gitignore"), "target/\n").unwrap();
+        let finders: Vec<fn(&Path) -> Finder> = vec![
+            |root| Finder::with_glob(root, "*.rs").unwrap(),
+            |root| Finder::with_glob(root, "*/src/**").unwrap().min_depth(3),
+            |root| Finder::with_matcher(root, EntryType::Dir).max_depth(2),
+            |root| {
+                Finder::with_glob(root, "*.rs")
+                    .unwrap()
+                    .prune(Pattern::exact("target"))
+                    .hidden(HiddenPolicy::Exclude)
+            },
+            |root| Finder::new(root, "mod.rs").ignore_files(true),
+            |root| {
+                Finder::new(root, "lib.txt")
+                    .add_root(root.join("crate1"))
+                    .dedup(true)
+            },
+        ];
+
+        for finder in finders {
+            let expected = sorted_paths(finder(root));
+            assert!(!expected.is_empty());
+            assert_eq!(sorted_paths(finder(root).threads(4).par_iter()), expected);
+            let ordered: Vec<_> = finder(root)
+                .threads(3)
+                .deterministic(true)
+                .par_iter()
+                .map(DirEntry::into_path)
+                .collect();
+            assert_eq!(ordered, expected);
+        }
+
+        let count = std::sync::atomic::AtomicUsize::new(0);
+        let errors = Finder::new(root, "mod.rs")
+            .error_policy(ErrorPolicy::Collect)
+            .par_for_each(|_| {
+                count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            });
+        assert_eq!(count.into_inner(), 24);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn parallel_panic() {
+        #[derive(Debug)]
+        struct Boom;
+
+        impl Matcher for Boom {
+            fn is_match(&self, entry: &DirEntry) -> Result<bool> {
+                assert_ne!(entry.file_name(), "boom", "matcher panicked");
+                Ok(true)
+            }
+        }
+
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        for dir in &["a/b", "c/d", "e"] {
+            std::fs::create_dir_all(tmp_dir.path().join(dir)).unwrap();
+        }
+        std::fs::File::create(tmp_dir.path().join("c/d/boom")).unwrap();
+        let finder = || Finder::with_matcher(tmp_dir.path(), Boom).threads(2);
+
+        // The panic reaches the caller, rather than leaving the other
+        // threads waiting for the directory which was being read.
+        let count = std::panic::catch_unwind(|| finder().par_iter().count());
+        assert!(count.is_err());
+        let sorted = std::panic::catch_unwind(|| finder().deterministic(true).par_iter().count());
+        assert!(sorted.is_err());
+        let for_each = std::panic::catch_unwind(|| finder().par_for_each(drop));
+        assert!(for_each.is_err());
+    }
+
+    #[test]
+    fn parallel_drop() {
+        #[derive(Debug)]
+        struct Slow(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+        impl Matcher for Slow {
+            fn is_match(&self, _: &DirEntry) -> Result<bool> {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+                self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(true)
+            }
+        }
+
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        for dir in &["a", "b", "c"] {
+            std::fs::create_dir_all(tmp_dir.path().join(dir)).unwrap();
+            for i in 0..50 {
+                std::fs::File::create(tmp_dir.path().join(dir).join(i.to_string())).unwrap();
+            }
+        }
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut iter = Finder::with_matcher(tmp_dir.path(), Slow(calls.clone()))
+            .threads(3)
+            .par_iter();
+        assert!(iter.next().is_some());
+
+        // Once the walk is dropped, its threads have finished.
+        drop(iter);
+        let after_drop = calls.load(std::sync::atomic::Ordering::SeqCst);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), after_drop);
+        assert!(after_drop < 150);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parallel_errors() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let finder = tree_with_error(&tmp_dir, "x").error_policy(ErrorPolicy::Yield);
+        let (found, errors): (Vec<_>, Vec<_>) = finder.par_try_iter().partition(|r| r.is_ok());
+        assert_eq!(
+            sorted_paths(found.into_iter().map(|r| r.unwrap())),
+            vec![tmp_dir.path().join("a/x"), tmp_dir.path().join("c/x")]
+        );
+        assert_eq!(errors.len(), 1);
+
+        let errors = Finder::new(tmp_dir.path(), "x")
+            .follow_links(true)
+            .error_policy(ErrorPolicy::Collect)
+            .par_for_each(|_| {});
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path(), Some(tmp_dir.path().join("b").as_path()));
+
+        let mut iter = Finder::new(tmp_dir.path(), "x")
+            .max_visited(2)
+            .error_policy(ErrorPolicy::Yield)
+            .par_try_iter();
+        assert!(matches!(
+            iter.next(),
+            Some(Err(Error::BudgetExceeded { limit: 2, .. }))
+        ));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn sorted_output() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path();
+        std::fs::create_dir_all(root.join("b/v10")).unwrap();
+        for (name, len) in &[("b/v2", 3), ("b/v10/z", 1), ("a", 2), ("C", 0)] {
+            std::fs::write(root.join(name), vec![b'x'; *len]).unwrap();
+        }
+        let a = std::fs::File::options()
+            .write(true)
+            .open(root.join("a"))
+            .unwrap();
+        a.set_modified(std::time::SystemTime::UNIX_EPOCH).unwrap();
+        let relative = |entries: Vec<DirEntry>| -> Vec<PathBuf> {
+            entries
+                .into_iter()
+                .map(|e| e.path().strip_prefix(root).unwrap().to_path_buf())
+                .collect()
+        };
+        let files = || Finder::with_matcher(root, EntryType::File);
+        let paths = |names: &[&str]| -> Vec<PathBuf> { names.iter().map(PathBuf::from).collect() };
+
+        let by_name = relative(files().sort_by(SortKey::Name).into_iter().collect());
+        assert_eq!(by_name, paths(&["C", "a", "b/v10/z", "b/v2"]));
+        let natural = relative(files().sort_by(SortKey::NaturalName).into_iter().collect());
+        assert_eq!(natural, paths(&["C", "a", "b/v2", "b/v10/z"]));
+
+        let sorted = |key| relative(files().sort_results(key).into_iter().collect());
+        assert_eq!(sorted(SortKey::Size), paths(&["C", "b/v10/z", "a", "b/v2"]));
+        assert_eq!(
+            sorted(SortKey::Depth),
+            paths(&["C", "a", "b/v2", "b/v10/z"])
+        );
+        assert_eq!(sorted(SortKey::Modified)[0], PathBuf::from("a"));
+        let parallel: Vec<_> = files()
+            .threads(2)
+            .sort_results(SortKey::Size)
+            .par_iter()
+            .collect();
+        assert_eq!(relative(parallel), sorted(SortKey::Size));
+    }
+
+    #[test]
+    fn breadth_first() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path();
+        for dir in &["a/b/c", "x", "z"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for name in &[
+            "a/b/c/config.toml",
+            "a/b/config.toml",
+            "x/config.toml",
+            "z/config.toml",
+        ] {
+            std::fs::File::create(root.join(name)).unwrap();
+        }
+        let finder = || Finder::new(root, "config.toml").sort_by(SortKey::Name);
+        let relative = |finder: Finder| -> Vec<PathBuf> {
+            finder
+                .into_iter()
+                .map(|e| e.path().strip_prefix(root).unwrap().to_path_buf())
+                .collect()
+        };
+        let paths = |names: &[&str]| -> Vec<PathBuf> { names.iter().map(PathBuf::from).collect() };
+
+        assert_eq!(
+            relative(finder()),
+            paths(&[
+                "a/b/c/config.toml",
+                "a/b/config.toml",
+                "x/config.toml",
+                "z/config.toml"
+            ])
+        );
+        assert_eq!(
+            relative(finder().breadth_first(true)),
+            paths(&[
+                "x/config.toml",
+                "z/config.toml",
+                "a/b/config.toml",
+                "a/b/c/config.toml"
+            ])
+        );
+        assert_eq!(
+            relative(finder().stop_at_shallowest(true)),
+            paths(&["x/config.toml", "z/config.toml"])
+        );
+        assert_eq!(
+            relative(finder().stop_at_shallowest(true).min_depth(3)),
+            paths(&["a/b/config.toml"])
+        );
+    }
+
+    /// Creates the tree searched by the tests of result limits, with three
+    /// text files in `d1`, two in `d2`, one in `d2/sub` and one at the top.
+    fn limits_tree() -> TempDir {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path();
+        std::fs::create_dir_all(root.join("d1")).unwrap();
+        std::fs::create_dir_all(root.join("d2/sub")).unwrap();
+        for name in &[
+            "d1/a.txt",
+            "d1/b.txt",
+            "d1/c.txt",
+            "d2/a.txt",
+            "d2/b.txt",
+            "d2/sub/a.txt",
+            "top.txt",
+        ] {
+            std::fs::File::create(root.join(name)).unwrap();
+        }
+        tmp_dir
+    }
+
+    /// Counts how many of `paths` are below each entry at `depth` below
+    /// `root`, in order of path, leaving out entries with none.
+    fn counts_below(root: &Path, depth: usize, paths: &[PathBuf]) -> Vec<usize> {
+        let mut counts = std::collections::BTreeMap::new();
+        for path in paths {
+            let relative = path.strip_prefix(root).unwrap();
+            let above: PathBuf = relative.components().take(depth).collect();
+            if relative.components().count() > depth {
+                *counts.entry(above).or_insert(0) += 1;
+            }
+        }
+        counts.into_values().collect()
+    }
+
+    #[test]
+    fn limit_results() {
+        let tmp_dir = limits_tree();
+        let root = tmp_dir.path();
+        let finder = || Finder::with_glob(root, "*.txt").unwrap();
+
+        assert_eq!(finder().max_results(2).into_iter().count(), 2);
+        assert_eq!(finder().max_results(0).into_iter().count(), 0);
+        let first = finder().sort_by(SortKey::Name).first().unwrap();
+        assert_eq!(first.path(), root.join("d1/a.txt"));
+        assert!(finder().exists());
+        assert!(!Finder::new(root, "missing").exists());
+    }
+
+    #[test]
+    fn limit_results_parallel() {
+        let tmp_dir = limits_tree();
+        let root = tmp_dir.path();
+        let finder = || Finder::with_glob(root, "*.txt").unwrap().threads(2);
+
+        assert_eq!(finder().max_results(2).par_iter().count(), 2);
+        assert_eq!(finder().max_results(0).par_iter().count(), 0);
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        finder().max_results(3).par_for_each(|_| {
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        });
+        assert_eq!(calls.into_inner(), 3);
+    }
+
+    #[test]
+    fn limit_results_per_dir() {
+        let tmp_dir = limits_tree();
+        let root = tmp_dir.path();
+        let finder = || {
+            Finder::with_glob(root, "*.txt")
+                .unwrap()
+                .max_results_per_dir(1)
+                .sort_by(SortKey::Name)
+        };
+
+        // The rest of each directory, including `d2/sub`, is skipped once it
+        // has yielded a match.
+        let sampled = vec![
+            root.join("d1/a.txt"),
+            root.join("d2/a.txt"),
+            root.join("top.txt"),
+        ];
+        assert_eq!(sorted_paths(finder()), sampled);
+        assert_eq!(sorted_paths(finder().breadth_first(true)), sampled);
+        // Nothing is skipped when directories are yielded last, so `d2/sub`
+        // yields a match of its own.
+        let contents_first = sorted_paths(finder().contents_first(true));
+        assert_eq!(contents_first.len(), 4);
+        assert_eq!(counts_below(root, 1, &contents_first), vec![1, 2]);
+    }
+
+    #[test]
+    fn limit_results_per_dir_parallel() {
+        let tmp_dir = limits_tree();
+        let root = tmp_dir.path();
+        let parallel = sorted_paths(
+            Finder::with_glob(root, "*.txt")
+                .unwrap()
+                .max_results_per_dir(1)
+                .sort_by(SortKey::Name)
+                .threads(2)
+                .par_iter(),
+        );
+
+        assert_eq!(parallel.len(), 3);
+        assert!(parallel.contains(&root.join("top.txt")));
+        assert_eq!(counts_below(root, 1, &parallel), vec![1, 1]);
+    }
+
+    #[test]
+    fn limit_results_per_subtree() {
+        let tmp_dir = limits_tree();
+        let root = tmp_dir.path();
+        let finder = || {
+            Finder::with_glob(root, "*.txt")
+                .unwrap()
+                .max_results_per_subtree(1, 2)
+                .sort_by(SortKey::Name)
+        };
+
+        let sampled = vec![
+            root.join("d1/a.txt"),
+            root.join("d1/b.txt"),
+            root.join("d2/a.txt"),
+            root.join("d2/b.txt"),
+            root.join("top.txt"),
+        ];
+        assert_eq!(sorted_paths(finder()), sampled);
+        assert_eq!(sorted_paths(finder().breadth_first(true)), sampled);
+        assert_eq!(sorted_paths(finder().contents_first(true)), sampled);
+    }
+
+    #[test]
+    fn limit_results_per_subtree_parallel() {
+        let tmp_dir = limits_tree();
+        let root = tmp_dir.path();
+        let parallel = sorted_paths(
+            Finder::with_glob(root, "*.txt")
+                .unwrap()
+                .max_results_per_subtree(1, 2)
+                .threads(2)
+                .par_iter(),
+        );
+
+        assert_eq!(parallel.len(), 5);
+        assert!(parallel.contains(&root.join("top.txt")));
+        assert_eq!(counts_below(root, 1, &parallel), vec![2, 2]);
+    }
+
+    #[test]
+    fn limit_results_per_dir_of_dirs() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let root = tmp_dir.path();
+        std::fs::create_dir_all(root.join("a")).unwrap();
+        std::fs::create_dir_all(root.join("b")).unwrap();
+        std::fs::File::create(root.join("a/x")).unwrap();
+        let finder = || {
+            Finder::with_glob(root, "*")
+                .unwrap()
+                .min_depth(1)
+                .max_results_per_dir(1)
+                .sort_by(SortKey::Name)
+        };
+
+        // The contents of a matching directory are still searched, in
+        // every mode.
+        let sampled = vec![root.join("a"), root.join("a/x")];
+        assert_eq!(sorted_paths(finder()), sampled);
+        assert_eq!(sorted_paths(finder().breadth_first(true)), sampled);
+        assert_eq!(sorted_paths(finder().contents_first(true)), sampled);
+        let parallel = sorted_paths(finder().threads(2).par_iter());
+        assert_eq!(parallel.len(), 2);
+        assert_eq!(counts_below(root, 1, &parallel), vec![1]);
+    }
+
+    #[test]
+    fn missing_root_is_an_error() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let finder = Finder::new(tmp_dir.path().join("missing"), "a");
+        let mut iter = finder.error_policy(ErrorPolicy::Yield).try_iter();
+
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
 }
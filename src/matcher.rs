@@ -0,0 +1,1080 @@
+use crate::pattern::{fold_case, name_bytes, relative_components};
+use crate::{CaseFold, DirEntry, Error, Pattern};
+use std::convert::TryFrom;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{FileType, Metadata};
+use std::io;
+use std::ops::{Bound, RangeBounds};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A condition which entries found by a [`Finder`] must satisfy.
+///
+/// Matchers are composed with [`and`], [`or`] and [`not`], or with
+/// [`any_of`] and [`all_of`] for lists, to describe queries such as
+/// "`*.log` files larger than 10 MB which are not under `target/`":
+///
+/// ```
+/// use where_is::{Matcher, Pattern, Size, Under};
+///
+/// let query = Pattern::glob("*.log")?
+///     .and(Size::larger_than(10 * 1024 * 1024))
+///     .and(Under::new("target").not());
+/// println!("{:?}", query);
+/// # Ok::<(), where_is::Error>(())
+/// ```
+///
+/// Every matcher implements `Debug`, so composed queries can be printed
+/// and inspected. Matchers are also `Send` and `Sync`, and may be shared
+/// between several `Finder`s by wrapping them in an `Arc`.
+///
+/// [`Finder`]: struct.Finder.html
+/// [`and`]: trait.Matcher.html#method.and
+/// [`or`]: trait.Matcher.html#method.or
+/// [`not`]: trait.Matcher.html#method.not
+/// [`any_of`]: fn.any_of.html
+/// [`all_of`]: fn.all_of.html
+pub trait Matcher: fmt::Debug + Send + Sync {
+    /// Returns whether `entry` satisfies this matcher.
+    ///
+    /// Matchers which inspect metadata report failures to read it as
+    /// errors, which are handled according to the search's
+    /// [`ErrorPolicy`].
+    ///
+    /// [`ErrorPolicy`]: enum.ErrorPolicy.html
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error>;
+
+    /// Matches entries which satisfy both `self` and `other`.
+    ///
+    /// `other` is only consulted if `self` matches.
+    fn and<M: Matcher>(self, other: M) -> And<Self, M>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    /// Matches entries which satisfy either `self` or `other`.
+    ///
+    /// `other` is only consulted if `self` does not match.
+    fn or<M: Matcher>(self, other: M) -> Or<Self, M>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+
+    /// Matches entries which do not satisfy `self`.
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+
+    /// Boxes the matcher, for use with [`any_of`] and [`all_of`].
+    ///
+    /// [`any_of`]: fn.any_of.html
+    /// [`all_of`]: fn.all_of.html
+    fn boxed(self) -> Box<dyn Matcher>
+    where
+        Self: Sized + 'static,
+    {
+        Box::new(self)
+    }
+}
+
+impl<M: Matcher + ?Sized> Matcher for Box<M> {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        (**self).is_match(entry)
+    }
+}
+
+impl<M: Matcher + ?Sized> Matcher for Arc<M> {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        (**self).is_match(entry)
+    }
+}
+
+impl<M: Matcher + ?Sized> Matcher for &M {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        (**self).is_match(entry)
+    }
+}
+
+impl Matcher for Pattern {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        Ok(self.matches(entry))
+    }
+}
+
+/// Matches entries which satisfy both of two matchers.
+///
+/// Created by [`Matcher::and`].
+///
+/// [`Matcher::and`]: trait.Matcher.html#method.and
+#[derive(Clone, Debug)]
+pub struct And<A, B>(pub A, pub B);
+
+impl<A: Matcher, B: Matcher> Matcher for And<A, B> {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        Ok(self.0.is_match(entry)? && self.1.is_match(entry)?)
+    }
+}
+
+/// Matches entries which satisfy either of two matchers.
+///
+/// Created by [`Matcher::or`].
+///
+/// [`Matcher::or`]: trait.Matcher.html#method.or
+#[derive(Clone, Debug)]
+pub struct Or<A, B>(pub A, pub B);
+
+impl<A: Matcher, B: Matcher> Matcher for Or<A, B> {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        Ok(self.0.is_match(entry)? || self.1.is_match(entry)?)
+    }
+}
+
+/// Matches entries which do not satisfy a matcher.
+///
+/// Created by [`Matcher::not`].
+///
+/// [`Matcher::not`]: trait.Matcher.html#method.not
+#[derive(Clone, Debug)]
+pub struct Not<M>(pub M);
+
+impl<M: Matcher> Matcher for Not<M> {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        Ok(!self.0.is_match(entry)?)
+    }
+}
+
+/// Matches entries which satisfy at least one of a list of matchers.
+///
+/// Created by [`any_of`]. An empty list matches nothing.
+///
+/// [`any_of`]: fn.any_of.html
+#[derive(Debug)]
+pub struct AnyOf(pub Vec<Box<dyn Matcher>>);
+
+/// Matches entries which satisfy any of `matchers`, consulting them in
+/// order until one matches.
+pub fn any_of<I: IntoIterator<Item = Box<dyn Matcher>>>(matchers: I) -> AnyOf {
+    AnyOf(matchers.into_iter().collect())
+}
+
+impl Matcher for AnyOf {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        for matcher in &self.0 {
+            if matcher.is_match(entry)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Matches entries which satisfy every one of a list of matchers.
+///
+/// Created by [`all_of`]. An empty list matches everything.
+///
+/// [`all_of`]: fn.all_of.html
+#[derive(Debug)]
+pub struct AllOf(pub Vec<Box<dyn Matcher>>);
+
+/// Matches entries which satisfy all of `matchers`, consulting them in
+/// order until one does not match.
+pub fn all_of<I: IntoIterator<Item = Box<dyn Matcher>>>(matchers: I) -> AllOf {
+    AllOf(matchers.into_iter().collect())
+}
+
+impl Matcher for AllOf {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        for matcher in &self.0 {
+            if !matcher.is_match(entry)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Matches entries whose file name has one of a set of extensions.
+///
+/// Extensions are given without the leading `.`, and compared exactly
+/// unless [`Extension::case_fold`] is set: `Extension::new("log")` matches
+/// `build.log` but not `build.LOG` or `build.log.gz`.
+///
+/// [`Extension::case_fold`]: struct.Extension.html#method.case_fold
+#[derive(Clone, Debug)]
+pub struct Extension {
+    extensions: Vec<String>,
+    case_fold: CaseFold,
+    // The bytes of each extension after case folding.
+    folded: Vec<Vec<u8>>,
+}
+
+impl Extension {
+    /// Matches entries with the extension `extension`.
+    pub fn new(extension: &str) -> Self {
+        Self::any([extension])
+    }
+
+    /// Matches entries with any of `extensions`.
+    pub fn any<I, S>(extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let extensions: Vec<String> = extensions
+            .into_iter()
+            .map(|e| e.as_ref().to_string())
+            .collect();
+        Extension {
+            folded: extensions.iter().map(|e| e.as_bytes().to_vec()).collect(),
+            extensions,
+            case_fold: CaseFold::default(),
+        }
+    }
+
+    /// Sets how letter case is treated, as for a [`Pattern`]. Defaults to
+    /// [`CaseFold::Sensitive`].
+    ///
+    /// [`Pattern`]: struct.Pattern.html
+    /// [`CaseFold::Sensitive`]: enum.CaseFold.html#variant.Sensitive
+    pub fn case_fold(mut self, case_fold: CaseFold) -> Self {
+        self.case_fold = case_fold;
+        self.folded = self
+            .extensions
+            .iter()
+            .map(|e| fold_case(e.as_bytes(), case_fold).into_owned())
+            .collect();
+        self
+    }
+}
+
+impl Matcher for Extension {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        Ok(match entry.path().extension() {
+            Some(ext) => {
+                let ext = name_bytes(ext);
+                let ext = fold_case(&ext, self.case_fold);
+                self.folded.iter().any(|e| *e == *ext)
+            }
+            None => false,
+        })
+    }
+}
+
+/// Matches entries strictly below a directory, given relative to the root
+/// of the search.
+///
+/// `Under::new("target")` matches `target/debug` and
+/// `target/debug/build.log`, but neither `target` itself nor
+/// `src/target/x`.
+#[derive(Clone, Debug)]
+pub struct Under {
+    dir: Vec<OsString>,
+}
+
+impl Under {
+    /// Matches entries below `dir`, relative to the root of the search.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Under {
+            dir: dir
+                .as_ref()
+                .components()
+                .filter(|c| !matches!(c, Component::CurDir))
+                .map(|c| c.as_os_str().to_os_string())
+                .collect(),
+        }
+    }
+}
+
+impl Matcher for Under {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        let components = relative_components(entry);
+        Ok(components.len() > self.dir.len()
+            && components.iter().zip(&self.dir).all(|(c, d)| *c == d))
+    }
+}
+
+/// Matches hidden entries, whose file names begin with `.`.
+///
+/// The roots of a search are never considered hidden, so that searching
+/// `.` or `~/.config` behaves as expected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hidden;
+
+impl Matcher for Hidden {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        Ok(entry.depth() > 0 && entry.file_name().as_encoded_bytes().first() == Some(&b'.'))
+    }
+}
+
+/// Which entries a [`Finder`] yields according to whether they are
+/// [`Hidden`].
+///
+/// [`Finder`]: struct.Finder.html
+/// [`Hidden`]: struct.Hidden.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HiddenPolicy {
+    /// Yield hidden entries along with all others.
+    #[default]
+    Include,
+    /// Yield only entries which are not hidden.
+    Exclude,
+    /// Yield only hidden entries.
+    Only,
+}
+
+/// Matches entries by their type.
+///
+/// Entries are described as the search visits them: when following
+/// symbolic links, an entry describes the target of its link, so a link to
+/// a directory matches [`EntryType::Dir`]. [`EntryType::Symlink`] is the
+/// exception, and always matches entries which are links themselves, whether
+/// or not they are followed. To check the type of a link's target without
+/// following links, use [`EntryType::target`].
+///
+/// Sockets, FIFOs and devices only exist on Unix; elsewhere, those types
+/// never match.
+///
+/// [`EntryType::Dir`]: enum.EntryType.html#variant.Dir
+/// [`EntryType::Symlink`]: enum.EntryType.html#variant.Symlink
+/// [`EntryType::target`]: enum.EntryType.html#method.target
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryType {
+    /// A regular file.
+    File,
+    /// A directory.
+    Dir,
+    /// A symbolic link.
+    Symlink,
+    /// A Unix domain socket.
+    Socket,
+    /// A named pipe.
+    Fifo,
+    /// A block device, such as a disk.
+    BlockDevice,
+    /// A character device, such as a terminal.
+    CharDevice,
+}
+
+impl EntryType {
+    /// Matches entries of this type after resolving symbolic links, in the
+    /// manner of `find -xtype`.
+    pub fn target(self) -> LinkTarget {
+        LinkTarget(self)
+    }
+
+    fn is_type_of(self, file_type: FileType) -> bool {
+        #[cfg(unix)]
+        use std::os::unix::fs::FileTypeExt;
+
+        match self {
+            EntryType::File => file_type.is_file(),
+            EntryType::Dir => file_type.is_dir(),
+            EntryType::Symlink => file_type.is_symlink(),
+            #[cfg(unix)]
+            EntryType::Socket => file_type.is_socket(),
+            #[cfg(unix)]
+            EntryType::Fifo => file_type.is_fifo(),
+            #[cfg(unix)]
+            EntryType::BlockDevice => file_type.is_block_device(),
+            #[cfg(unix)]
+            EntryType::CharDevice => file_type.is_char_device(),
+            #[cfg(not(unix))]
+            _ => false,
+        }
+    }
+}
+
+impl Matcher for EntryType {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        Ok(match self {
+            EntryType::Symlink => entry.path_is_symlink(),
+            _ => self.is_type_of(entry.file_type()),
+        })
+    }
+}
+
+/// Matches entries by the type of their target, after resolving symbolic
+/// links, whether or not the search follows them. Created with
+/// [`EntryType::target`].
+///
+/// Entries which are not links match by their own type. Broken links,
+/// which have no target, match [`EntryType::Symlink`].
+///
+/// [`EntryType::target`]: enum.EntryType.html#method.target
+/// [`EntryType::Symlink`]: enum.EntryType.html#variant.Symlink
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LinkTarget(pub EntryType);
+
+impl Matcher for LinkTarget {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        if !entry.path_is_symlink() {
+            return Ok(self.0.is_type_of(entry.file_type()));
+        }
+        match std::fs::metadata(entry.path()) {
+            Ok(md) => Ok(self.0.is_type_of(md.file_type())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Ok(self.0 == EntryType::Symlink)
+            }
+            Err(err) => Err(io_error(entry, err)),
+        }
+    }
+}
+
+/// Matches entries by the size of their contents, in bytes.
+///
+/// All bounds are inclusive unless stated otherwise. Sizes may be written
+/// with units using [`Size::parse_bytes`].
+///
+/// Directories and other special files have platform-specific sizes; use
+/// [`EntryType::File`] to restrict matches to regular files, or [`Empty`]
+/// to find empty directories.
+///
+/// [`Size::parse_bytes`]: struct.Size.html#method.parse_bytes
+/// [`EntryType::File`]: enum.EntryType.html#variant.File
+/// [`Empty`]: struct.Empty.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Size {
+    min: u64,
+    max: u64,
+}
+
+impl Size {
+    /// Matches entries of exactly `bytes`.
+    pub fn exactly(bytes: u64) -> Self {
+        Size {
+            min: bytes,
+            max: bytes,
+        }
+    }
+
+    /// Matches entries strictly larger than `bytes`.
+    pub fn larger_than(bytes: u64) -> Self {
+        Self::range((Bound::Excluded(bytes), Bound::Unbounded))
+    }
+
+    /// Matches entries strictly smaller than `bytes`.
+    pub fn smaller_than(bytes: u64) -> Self {
+        Self::range(..bytes)
+    }
+
+    /// Matches entries whose size lies within `range`, such as
+    /// `Size::range(1024..=4096)` or `Size::range(1024..)`.
+    pub fn range<R: RangeBounds<u64>>(range: R) -> Self {
+        let min = match range.start_bound() {
+            Bound::Included(&min) => Some(min),
+            Bound::Excluded(&min) => min.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let max = match range.end_bound() {
+            Bound::Included(&max) => Some(max),
+            Bound::Excluded(&max) => max.checked_sub(1),
+            Bound::Unbounded => Some(u64::MAX),
+        };
+        match (min, max) {
+            (Some(min), Some(max)) => Size { min, max },
+            // The range admits no size at all.
+            _ => Size { min: 1, max: 0 },
+        }
+    }
+
+    /// Parses a size such as `512`, `10M`, `1.5GB` or `1GiB` into a number
+    /// of bytes.
+    ///
+    /// Units are not case-sensitive. `K`, `M`, `G`, `T` and `P`, alone or
+    /// followed by `iB`, are powers of 1024, as with `du` and `find`; when
+    /// followed by `B` alone, they are powers of 1000. A bare number, or
+    /// one followed by `B`, is a number of bytes.
+    pub fn parse_bytes(size: &str) -> Result<u64, Error> {
+        let invalid = || Error::InvalidValue {
+            value: size.to_string(),
+            expected: "a size such as 512, 10M or 1GiB",
+        };
+        let trimmed = size.trim();
+        let split = trimmed
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(trimmed.len());
+        let (number, unit) = trimmed.split_at(split);
+        let (power, base) = match unit.trim_start().to_ascii_lowercase().as_str() {
+            "" | "b" => (0, 1024),
+            "k" | "kib" => (1, 1024),
+            "m" | "mib" => (2, 1024),
+            "g" | "gib" => (3, 1024),
+            "t" | "tib" => (4, 1024),
+            "p" | "pib" => (5, 1024),
+            "kb" => (1, 1000),
+            "mb" => (2, 1000),
+            "gb" => (3, 1000),
+            "tb" => (4, 1000),
+            "pb" => (5, 1000),
+            _ => return Err(invalid()),
+        };
+        let scale = (base as u128).pow(power);
+        let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+        if whole.is_empty() && fraction.is_empty() || fraction.contains('.') {
+            return Err(invalid());
+        }
+        let parse = |digits: &str| -> Result<u128, Error> {
+            if digits.is_empty() {
+                Ok(0)
+            } else {
+                digits.parse().map_err(|_| invalid())
+            }
+        };
+        // Fractions are truncated to a whole number of bytes.
+        let fraction_scale = 10u128.checked_pow(fraction.len() as u32);
+        let fraction_bytes = parse(fraction)?
+            .checked_mul(scale)
+            .zip(fraction_scale)
+            .map(|(fraction, fraction_scale)| fraction / fraction_scale);
+        let bytes = parse(whole)?
+            .checked_mul(scale)
+            .zip(fraction_bytes)
+            .and_then(|(whole, fraction)| whole.checked_add(fraction))
+            .ok_or_else(invalid)?;
+        u64::try_from(bytes).map_err(|_| invalid())
+    }
+}
+
+impl Matcher for Size {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        let len = entry.metadata()?.len();
+        Ok(self.min <= len && len <= self.max)
+    }
+}
+
+/// Matches empty regular files and empty directories, like `find -empty`.
+///
+/// Other kinds of entries never match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Empty;
+
+impl Matcher for Empty {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        let file_type = entry.file_type();
+        if file_type.is_file() {
+            Ok(entry.metadata()?.len() == 0)
+        } else if file_type.is_dir() {
+            let mut entries =
+                std::fs::read_dir(entry.path()).map_err(|err| io_error(entry, err))?;
+            Ok(entries.next().is_none())
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+/// Selects which of an entry's timestamps a [`Time`] matcher inspects.
+///
+/// [`Time`]: struct.Time.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timestamp {
+    /// The time the contents were last modified.
+    Modified,
+    /// The time the contents were last read.
+    Accessed,
+    /// The time the entry's metadata, such as its permissions or owner,
+    /// last changed. Only recorded on Unix; elsewhere, it is reported as
+    /// an error.
+    Changed,
+    /// The time the entry was created. Not all platforms and filesystems
+    /// record this; where unavailable, it is reported as an error.
+    Created,
+}
+
+impl Timestamp {
+    fn of(self, md: &Metadata) -> io::Result<SystemTime> {
+        match self {
+            Timestamp::Modified => md.modified(),
+            Timestamp::Accessed => md.accessed(),
+            Timestamp::Changed => changed(md),
+            Timestamp::Created => md.created(),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn changed(md: &Metadata) -> io::Result<SystemTime> {
+    use std::os::unix::fs::MetadataExt;
+
+    Ok(unix_time(md.ctime(), md.ctime_nsec() as u32))
+}
+
+/// Returns the time `secs` seconds and then `nanos` nanoseconds after the
+/// Unix epoch, as in a `timespec`, where `secs` may be negative but
+/// `nanos` always counts forward.
+fn unix_time(secs: i64, nanos: u32) -> SystemTime {
+    let whole = Duration::from_secs(secs.unsigned_abs());
+    let time = if secs >= 0 {
+        UNIX_EPOCH + whole
+    } else {
+        UNIX_EPOCH - whole
+    };
+    time + Duration::from_nanos(nanos.into())
+}
+
+#[cfg(not(unix))]
+fn changed(_: &Metadata) -> io::Result<SystemTime> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "change time is not available on this platform",
+    ))
+}
+
+/// Matches entries whose timestamp lies within a range.
+///
+/// Bounds may be absolute, relative to the present, or taken from a
+/// reference file in the manner of `find -newer`:
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use where_is::Time;
+///
+/// let modified_today = Time::modified().newer_than(Duration::from_secs(24 * 60 * 60));
+/// let stale = Time::accessed().older_than(Time::parse_duration("30 days")?);
+/// let this_year = Time::modified().after(Time::parse_time("2026-01-01")?);
+/// let since_stamp = Time::modified().after_file("build.stamp")?;
+/// # Ok::<(), where_is::Error>(())
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Time {
+    stamp: Timestamp,
+    after: Option<SystemTime>,
+    before: Option<SystemTime>,
+}
+
+impl Time {
+    /// Matches entries by `stamp`. Until bounds are added with
+    /// [`after`](#method.after) or [`before`](#method.before), every entry
+    /// matches.
+    pub fn new(stamp: Timestamp) -> Self {
+        Time {
+            stamp,
+            after: None,
+            before: None,
+        }
+    }
+
+    /// Matches entries by modification time.
+    pub fn modified() -> Self {
+        Self::new(Timestamp::Modified)
+    }
+
+    /// Matches entries by access time.
+    pub fn accessed() -> Self {
+        Self::new(Timestamp::Accessed)
+    }
+
+    /// Matches entries by metadata change time.
+    pub fn changed() -> Self {
+        Self::new(Timestamp::Changed)
+    }
+
+    /// Matches entries by creation time.
+    pub fn created() -> Self {
+        Self::new(Timestamp::Created)
+    }
+
+    /// Only matches entries whose timestamp is strictly later than `time`.
+    pub fn after(mut self, time: SystemTime) -> Self {
+        self.after = Some(time);
+        self
+    }
+
+    /// Only matches entries whose timestamp is strictly earlier than
+    /// `time`.
+    pub fn before(mut self, time: SystemTime) -> Self {
+        self.before = Some(time);
+        self
+    }
+
+    /// Only matches entries whose timestamp is less than `age` ago.
+    ///
+    /// The age is measured from when this method is called, not from when
+    /// each entry is visited.
+    pub fn newer_than(self, age: Duration) -> Self {
+        self.after(ago(age))
+    }
+
+    /// Only matches entries whose timestamp is more than `age` ago.
+    ///
+    /// The age is measured from when this method is called, not from when
+    /// each entry is visited.
+    pub fn older_than(self, age: Duration) -> Self {
+        self.before(ago(age))
+    }
+
+    /// Only matches entries whose timestamp is strictly later than the
+    /// same timestamp of the file at `path`, such as a stamp file written
+    /// by the previous build.
+    ///
+    /// The reference file is read immediately, and symbolic links to it
+    /// are followed.
+    pub fn after_file<P: AsRef<Path>>(self, path: P) -> Result<Self, Error> {
+        let time = self.stamp_of(path.as_ref())?;
+        Ok(self.after(time))
+    }
+
+    /// Only matches entries whose timestamp is strictly earlier than the
+    /// same timestamp of the file at `path`.
+    ///
+    /// The reference file is read immediately, and symbolic links to it
+    /// are followed.
+    pub fn before_file<P: AsRef<Path>>(self, path: P) -> Result<Self, Error> {
+        let time = self.stamp_of(path.as_ref())?;
+        Ok(self.before(time))
+    }
+
+    fn stamp_of(&self, path: &Path) -> Result<SystemTime, Error> {
+        std::fs::metadata(path)
+            .and_then(|md| self.stamp.of(&md))
+            .map_err(|source| Error::Io {
+                path: Some(path.to_path_buf()),
+                depth: None,
+                source,
+            })
+    }
+
+    /// Parses a date such as `2026-01-01`, or a date and time such as
+    /// `2026-01-01 12:30` or `2026-01-01T12:30:45Z`.
+    ///
+    /// Times are in UTC, and a date alone refers to its midnight.
+    pub fn parse_time(time: &str) -> Result<SystemTime, Error> {
+        let invalid = || Error::InvalidValue {
+            value: time.to_string(),
+            expected: "a date such as 2026-01-01 or 2026-01-01 12:30:45",
+        };
+        let trimmed = time.trim();
+        let trimmed = trimmed.strip_suffix(['Z', 'z']).unwrap_or(trimmed);
+        let (date, clock) = match trimmed.split_once([' ', 'T', 't']) {
+            Some((date, clock)) => (date, Some(clock)),
+            None => (trimmed, None),
+        };
+        let numbers = |s: &str, sep: char| -> Option<Vec<u32>> {
+            s.split(sep)
+                .map(|n| {
+                    if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) {
+                        n.parse().ok()
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        };
+        let (year, month, day) = match numbers(date, '-').as_deref() {
+            Some(&[year, month, day]) => (year, month, day),
+            _ => return Err(invalid()),
+        };
+        let (hour, minute, second) = match clock.map(|clock| numbers(clock, ':')) {
+            None => (0, 0, 0),
+            Some(Some(clock)) => match clock[..] {
+                [hour, minute] => (hour, minute, 0),
+                [hour, minute, second] => (hour, minute, second),
+                _ => return Err(invalid()),
+            },
+            Some(None) => return Err(invalid()),
+        };
+        if !(1..=12).contains(&month)
+            || day == 0
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 59
+        {
+            return Err(invalid());
+        }
+        let days = days_from_civil(year as i64, month, day);
+        let seconds = days * 86_400 + (hour * 3600 + minute * 60 + second) as i64;
+        Ok(unix_time(seconds, 0))
+    }
+
+    /// Parses a duration such as `90s`, `15 min`, `2 hours`, `30d` or
+    /// `1 week`.
+    ///
+    /// Units may be written as `s`, `m`, `h`, `d` or `w`, or spelled out,
+    /// in the singular or plural.
+    pub fn parse_duration(duration: &str) -> Result<Duration, Error> {
+        let invalid = || Error::InvalidValue {
+            value: duration.to_string(),
+            expected: "a duration such as 2h or 30 days",
+        };
+        let trimmed = duration.trim();
+        let split = trimmed
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(trimmed.len());
+        let (number, unit) = trimmed.split_at(split);
+        let number: u64 = number.parse().map_err(|_| invalid())?;
+        let unit = unit.trim_start().to_ascii_lowercase();
+        let seconds = match unit.as_str() {
+            "" | "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60,
+            "h" | "hr" | "hrs" | "hour" | "hours" => 60 * 60,
+            "d" | "day" | "days" => 24 * 60 * 60,
+            "w" | "week" | "weeks" => 7 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        number
+            .checked_mul(seconds)
+            .map(Duration::from_secs)
+            .ok_or_else(invalid)
+    }
+}
+
+impl Matcher for Time {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        let md = entry.metadata()?;
+        let time = self.stamp.of(&md).map_err(|err| io_error(entry, err))?;
+        Ok(self.after.is_none_or(|after| time > after)
+            && self.before.is_none_or(|before| time < before))
+    }
+}
+
+/// Returns the time `age` before now, or the epoch if that would precede
+/// it.
+fn ago(age: Duration) -> SystemTime {
+    SystemTime::now().checked_sub(age).unwrap_or(UNIX_EPOCH)
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Returns the number of days between the Unix epoch and the given date in
+/// the proleptic Gregorian calendar, using Howard Hinnant's algorithm.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+pub(crate) fn io_error(entry: &DirEntry, err: std::io::Error) -> Error {
+    Error::Io {
+        path: Some(PathBuf::from(entry.path())),
+        depth: Some(entry.depth()),
+        source: err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::dir_entry;
+    use tempdir::TempDir;
+
+    #[test]
+    fn combinators() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("target/debug")).unwrap();
+        std::fs::write(tmp_dir.path().join("big.log"), vec![0; 100]).unwrap();
+        std::fs::write(tmp_dir.path().join("small.log"), vec![0; 10]).unwrap();
+        std::fs::write(tmp_dir.path().join("target/debug/big.log"), vec![0; 100]).unwrap();
+
+        let query = Pattern::glob("*.log")
+            .unwrap()
+            .and(Size::larger_than(50))
+            .and(Under::new("target").not());
+        let matches = |relative: &str| {
+            query
+                .is_match(&dir_entry(tmp_dir.path(), relative))
+                .unwrap()
+        };
+
+        assert!(matches("big.log"));
+        assert!(!matches("small.log"));
+        assert!(!matches("target/debug/big.log"));
+        assert!(!matches("target"));
+    }
+
+    #[test]
+    fn lists() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::write(tmp_dir.path().join("a.rs"), "").unwrap();
+        let a = dir_entry(tmp_dir.path(), "a.rs");
+
+        assert!(!any_of(vec![]).is_match(&a).unwrap());
+        assert!(all_of(vec![]).is_match(&a).unwrap());
+        assert!(any_of(vec![
+            Extension::new("toml").boxed(),
+            EntryType::File.boxed()
+        ])
+        .is_match(&a)
+        .unwrap());
+        assert!(!all_of(vec![
+            Extension::any(["rs", "toml"]).boxed(),
+            EntryType::Dir.boxed()
+        ])
+        .is_match(&a)
+        .unwrap());
+    }
+
+    #[test]
+    fn extension_case() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::write(tmp_dir.path().join("photo.JPG"), "").unwrap();
+        std::fs::write(tmp_dir.path().join("notes.\u{c9}T\u{c9}"), "").unwrap();
+        let photo = dir_entry(tmp_dir.path(), "photo.JPG");
+        let notes = dir_entry(tmp_dir.path(), "notes.\u{c9}T\u{c9}");
+        let matches = |ext: Extension, entry| ext.is_match(entry).unwrap();
+
+        assert!(!matches(Extension::new("jpg"), &photo));
+        assert!(matches(
+            Extension::new("jpg").case_fold(CaseFold::Ascii),
+            &photo
+        ));
+        assert!(!matches(
+            Extension::new("\u{e9}t\u{e9}").case_fold(CaseFold::Ascii),
+            &notes
+        ));
+        assert!(matches(
+            Extension::new("\u{e9}t\u{e9}").case_fold(CaseFold::Unicode),
+            &notes
+        ));
+    }
+
+    #[test]
+    fn size_units() {
+        let parse = |size| Size::parse_bytes(size).ok();
+
+        assert_eq!(parse("512"), Some(512));
+        assert_eq!(parse("10M"), Some(10 << 20));
+        assert_eq!(parse("1GiB"), Some(1 << 30));
+        assert_eq!(parse("1gb"), Some(1_000_000_000));
+        assert_eq!(parse("1.5k"), Some(1536));
+        assert_eq!(parse("2 KB"), Some(2000));
+        assert_eq!(parse("16E"), None);
+        assert_eq!(parse("M"), None);
+        assert_eq!(parse("1.2.3"), None);
+        assert_eq!(parse("20000P"), None);
+    }
+
+    #[test]
+    fn size_ranges_and_empty() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("full/empty")).unwrap();
+        std::fs::write(tmp_dir.path().join("full/ten"), vec![0; 10]).unwrap();
+        std::fs::write(tmp_dir.path().join("full/zero"), "").unwrap();
+        let ten = dir_entry(tmp_dir.path(), "full/ten");
+        let zero = dir_entry(tmp_dir.path(), "full/zero");
+
+        assert!(Size::exactly(10).is_match(&ten).unwrap());
+        assert!(Size::range(10..=20).is_match(&ten).unwrap());
+        assert!(!Size::range(..10).is_match(&ten).unwrap());
+        assert!(!Size::range(11..).is_match(&ten).unwrap());
+        assert!(Size::smaller_than(1).is_match(&zero).unwrap());
+        assert!(!Size::smaller_than(0).is_match(&zero).unwrap());
+
+        let empty = |relative: &str| {
+            Empty
+                .is_match(&dir_entry(tmp_dir.path(), relative))
+                .unwrap()
+        };
+        assert!(empty("full/empty"));
+        assert!(empty("full/zero"));
+        assert!(!empty("full/ten"));
+        assert!(!empty("full"));
+    }
+
+    #[test]
+    fn time_bounds() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::write(tmp_dir.path().join("a"), "").unwrap();
+        let a = dir_entry(tmp_dir.path(), "a");
+        let hour = std::time::Duration::from_secs(3600);
+        let now = SystemTime::now();
+
+        assert!(Time::modified().after(now - hour).is_match(&a).unwrap());
+        assert!(!Time::modified().before(now - hour).is_match(&a).unwrap());
+        assert!(Time::modified().before(now + hour).is_match(&a).unwrap());
+    }
+
+    #[test]
+    fn relative_and_reference_times() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let set_modified = |name: &str, secs: u64| {
+            let file = std::fs::File::create(tmp_dir.path().join(name)).unwrap();
+            file.set_modified(UNIX_EPOCH + Duration::from_secs(secs))
+                .unwrap();
+        };
+        set_modified("old", 1000);
+        set_modified("stamp", 2000);
+        std::fs::write(tmp_dir.path().join("new"), "").unwrap();
+        let old = dir_entry(tmp_dir.path(), "old");
+        let new = dir_entry(tmp_dir.path(), "new");
+        let hour = Duration::from_secs(3600);
+
+        let since_stamp = Time::modified()
+            .after_file(tmp_dir.path().join("stamp"))
+            .unwrap();
+        assert!(since_stamp.is_match(&new).unwrap());
+        assert!(!since_stamp.is_match(&old).unwrap());
+        assert!(Time::modified().older_than(hour).is_match(&old).unwrap());
+        assert!(!Time::modified().older_than(hour).is_match(&new).unwrap());
+        assert!(Time::changed().newer_than(hour).is_match(&old).unwrap());
+        assert!(Time::modified()
+            .after_file(tmp_dir.path().join("missing"))
+            .is_err());
+    }
+
+    #[test]
+    fn parse_times_and_durations() {
+        let time = |s| Time::parse_time(s).ok();
+        let secs = |secs| Some(UNIX_EPOCH + Duration::from_secs(secs));
+
+        assert_eq!(time("1970-01-01"), secs(0));
+        assert_eq!(time("2026-01-01"), secs(1_767_225_600));
+        assert_eq!(time("2000-02-29T12:00:00Z"), secs(951_825_600));
+        assert_eq!(time("2000-02-29 12:00"), secs(951_825_600));
+        assert_eq!(
+            time("1969-12-31 23:59:59"),
+            Some(UNIX_EPOCH - Duration::from_secs(1))
+        );
+        assert_eq!(
+            unix_time(-2, 250_000_000),
+            UNIX_EPOCH - Duration::from_millis(1750)
+        );
+        assert_eq!(time("2026-02-29"), None);
+        assert_eq!(time("2026-1"), None);
+        assert_eq!(time("2026-01-01 24:00"), None);
+        assert_eq!(time("yesterday"), None);
+
+        let duration = |s| Time::parse_duration(s).ok().map(|d| d.as_secs());
+        assert_eq!(duration("90s"), Some(90));
+        assert_eq!(duration("15 min"), Some(900));
+        assert_eq!(duration("2 hours"), Some(7200));
+        assert_eq!(duration("30d"), Some(30 * 86_400));
+        assert_eq!(duration("1 week"), Some(7 * 86_400));
+        assert_eq!(duration("3 fortnights"), None);
+        assert_eq!(duration("hours"), None);
+    }
+
+    #[test]
+    fn metadata_errors_are_reported() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        std::fs::write(tmp_dir.path().join("a"), "").unwrap();
+        let a = dir_entry(tmp_dir.path(), "a");
+        std::fs::remove_file(tmp_dir.path().join("a")).unwrap();
+
+        let err = Size::larger_than(0).is_match(&a).unwrap_err();
+        assert_eq!(err.path(), Some(tmp_dir.path().join("a").as_path()));
+        assert_eq!(err.io_error_kind(), Some(std::io::ErrorKind::NotFound));
+    }
+}
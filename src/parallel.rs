@@ -0,0 +1,267 @@
+use crate::search::{Search, Task};
+use crate::{DirEntry, Result};
+use crossbeam_deque::{Injector, Stealer, Worker};
+use crossbeam_utils::Backoff;
+use std::iter;
+use std::mem;
+use std::panic;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::vec;
+
+/// How many results the threads of a parallel search may get ahead of the
+/// caller consuming them.
+const CHANNEL_BOUND: usize = 1024;
+
+/// Searches whose directories are read by a pool of threads, each taking
+/// directories from its own queue and stealing from the others when that
+/// runs dry.
+impl Search {
+    /// Runs the search on `threads` background threads, sending its
+    /// results to the returned walk.
+    pub(crate) fn spawn(self, threads: usize, deterministic: bool) -> ParWalk {
+        let (tx, rx) = mpsc::sync_channel(CHANNEL_BOUND);
+        let search = Arc::new(self);
+        let shared = Arc::clone(&search);
+        let thread =
+            thread::spawn(move || shared.run(threads, &|root, item| tx.send((root, item)).is_ok()));
+        ParWalk {
+            rx,
+            thread: Some(thread),
+            search,
+            deterministic,
+            sorted: None,
+        }
+    }
+
+    /// Runs the search to completion on `threads` threads, passing each
+    /// matching entry and each error to `sink` along with the index of its
+    /// root. The search stops early if `sink` returns false.
+    ///
+    /// If a thread panics, for instance in a matcher, the search stops and
+    /// the panic is resumed once every thread has finished.
+    pub(crate) fn run<F>(&self, threads: usize, sink: &F)
+    where
+        F: Fn(usize, Result<DirEntry>) -> bool + Sync,
+    {
+        if self.options.is_empty() {
+            return;
+        }
+        let workers: Vec<_> = (0..threads.max(1)).map(|_| Worker::new_lifo()).collect();
+        let pool = Pool {
+            injector: Injector::new(),
+            stealers: workers.iter().map(Worker::stealer).collect(),
+            pending: AtomicUsize::new(0),
+            sleeping: AtomicUsize::new(0),
+            lock: Mutex::new(()),
+            wake: Condvar::new(),
+        };
+        for (root, path) in self.roots.iter().enumerate() {
+            if let Some(task) = self.start(root, path, &mut |root, item| sink(root, item)) {
+                pool.pending.fetch_add(1, Ordering::SeqCst);
+                pool.injector.push(task);
+            }
+        }
+        thread::scope(|scope| {
+            for local in workers {
+                let pool = &pool;
+                scope.spawn(move || self.work(local, pool, sink));
+            }
+        });
+    }
+
+    /// Reads directories until none remain, or the search is stopped.
+    fn work<F>(&self, local: Worker<Task>, pool: &Pool, sink: &F)
+    where
+        F: Fn(usize, Result<DirEntry>) -> bool,
+    {
+        let mut queue = |task| {
+            pool.pending.fetch_add(1, Ordering::SeqCst);
+            local.push(task);
+            pool.wake(false);
+        };
+        let backoff = Backoff::new();
+        while !self.quit.load(Ordering::Relaxed) {
+            match find_task(&local, &pool.injector, &pool.stealers) {
+                Some(task) => {
+                    backoff.reset();
+                    let _reading = Reading { search: self, pool };
+                    self.read_dir(task, &mut queue, &mut |root, item| sink(root, item));
+                }
+                None if pool.pending.load(Ordering::SeqCst) == 0 => return,
+                None if backoff.is_completed() => pool.sleep(self),
+                None => backoff.snooze(),
+            }
+        }
+    }
+}
+
+/// The queues of a parallel search, shared by its threads.
+struct Pool {
+    injector: Injector<Task>,
+    stealers: Vec<Stealer<Task>>,
+    // The number of directories waiting to be read, or being read.
+    pending: AtomicUsize,
+    // The number of threads waiting for directories to read.
+    sleeping: AtomicUsize,
+    lock: Mutex<()>,
+    wake: Condvar,
+}
+
+impl Pool {
+    /// Waits until there may be a directory to read, or the search is over.
+    fn sleep(&self, search: &Search) {
+        self.sleeping.fetch_add(1, Ordering::SeqCst);
+        let guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        // Checked under the lock, which is also held while waking threads,
+        // so that a wakeup cannot be missed in between.
+        let idle = !search.quit.load(Ordering::SeqCst)
+            && self.pending.load(Ordering::SeqCst) != 0
+            && self.injector.is_empty()
+            && self.stealers.iter().all(Stealer::is_empty);
+        if idle {
+            drop(self.wake.wait(guard));
+        }
+        self.sleeping.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Wakes one sleeping thread, or all of them.
+    fn wake(&self, all: bool) {
+        if self.sleeping.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        if all {
+            self.wake.notify_all();
+        } else {
+            self.wake.notify_one();
+        }
+    }
+}
+
+/// Marks a directory as read once dropped, even if reading it panicked, so
+/// that the other threads of the search do not wait for it forever.
+struct Reading<'a> {
+    search: &'a Search,
+    pool: &'a Pool,
+}
+
+impl Drop for Reading<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.search.quit.store(true, Ordering::SeqCst);
+        }
+        let done = self.pool.pending.fetch_sub(1, Ordering::SeqCst) == 1;
+        if done || self.search.quit.load(Ordering::SeqCst) {
+            self.pool.wake(true);
+        }
+    }
+}
+
+/// Takes the next directory to read, preferring the thread's own queue,
+/// then the roots, then stealing from other threads.
+fn find_task(
+    local: &Worker<Task>,
+    injector: &Injector<Task>,
+    stealers: &[Stealer<Task>],
+) -> Option<Task> {
+    local.pop().or_else(|| {
+        iter::repeat_with(|| {
+            injector
+                .steal_batch_and_pop(local)
+                .or_else(|| stealers.iter().map(Stealer::steal).collect())
+        })
+        .find(|steal| !steal.is_retry())
+        .and_then(|steal| steal.success())
+    })
+}
+
+/// The results of a parallel search, as they are produced by its threads.
+///
+/// Created by [`Finder::par_iter`] and [`Finder::par_try_iter`]. Dropping
+/// the walk stops the search, and waits for its threads to finish.
+///
+/// [`Finder::par_iter`]: struct.Finder.html#method.par_iter
+/// [`Finder::par_try_iter`]: struct.Finder.html#method.par_try_iter
+pub struct ParWalk {
+    rx: Receiver<(usize, Result<DirEntry>)>,
+    // The thread running the search, until it has finished.
+    thread: Option<JoinHandle<()>>,
+    search: Arc<Search>,
+    deterministic: bool,
+    sorted: Option<vec::IntoIter<Result<DirEntry>>>,
+}
+
+impl ParWalk {
+    /// Waits for the search to finish once it has sent every result,
+    /// resuming any panic raised by one of its threads.
+    fn finish(&mut self) {
+        if let Some(Err(panic)) = self.thread.take().map(JoinHandle::join) {
+            panic::resume_unwind(panic);
+        }
+    }
+}
+
+impl Drop for ParWalk {
+    fn drop(&mut self) {
+        if self.thread.is_none() {
+            return;
+        }
+        self.search.quit.store(true, Ordering::SeqCst);
+        // Closing the channel wakes any thread waiting to send a result.
+        let (_, closed) = mpsc::sync_channel(0);
+        drop(mem::replace(&mut self.rx, closed));
+        // A panic in the search is not resumed, since its results are no
+        // longer wanted, and the walk may be dropped while unwinding.
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Iterator for ParWalk {
+    type Item = Result<DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.deterministic {
+            let item = self.rx.recv().ok().map(|(_, item)| item);
+            if item.is_none() {
+                self.finish();
+            }
+            return item;
+        }
+        if self.sorted.is_none() {
+            let items = self.rx.iter().collect();
+            self.finish();
+            self.sorted = Some(sorted(items));
+        }
+        self.sorted.as_mut()?.next()
+    }
+}
+
+/// Orders the results of a search by root, then by path, so that each
+/// directory is followed by its contents in order of name. Errors without
+/// a path come last.
+fn sorted(mut items: Vec<(usize, Result<DirEntry>)>) -> vec::IntoIter<Result<DirEntry>> {
+    fn path(item: &Result<DirEntry>) -> Option<&Path> {
+        match item {
+            Ok(entry) => Some(entry.path()),
+            Err(err) => err.path(),
+        }
+    }
+    items.sort_by(|(a_root, a), (b_root, b)| {
+        let (a, b) = (path(a), path(b));
+        a_root
+            .cmp(b_root)
+            .then(a.is_none().cmp(&b.is_none()))
+            .then(a.cmp(&b))
+    });
+    items
+        .into_iter()
+        .map(|(_, item)| item)
+        .collect::<Vec<_>>()
+        .into_iter()
+}
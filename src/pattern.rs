@@ -0,0 +1,559 @@
+use crate::{DirEntry, Error};
+use globset::{GlobBuilder, GlobMatcher};
+use regex::bytes::{Regex, RegexBuilder};
+use regex_syntax::hir::{
+    Capture, Class, ClassUnicode, ClassUnicodeRange, Hir, HirKind, Literal, Repetition,
+};
+use regex_syntax::ParserBuilder;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use unicode_normalization::{is_nfc_quick, is_nfd_quick, IsNormalized, UnicodeNormalization};
+
+/// Selects which portion of an entry's path a [`Pattern`] is tested against.
+///
+/// [`Pattern`]: struct.Pattern.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatchOn {
+    /// Match against the final component of the path (the file name).
+    #[default]
+    FileName,
+    /// Match against the path relative to the root of the search, using
+    /// `/` as the separator on all platforms.
+    ///
+    /// The root itself has an empty relative path.
+    RelativePath,
+}
+
+/// How letter case is treated when comparing names.
+///
+/// Every kind of [`Pattern`] folds both itself and the name being tested.
+/// For regular expressions and globs, only literal text is folded in full;
+/// character classes, such as `[A-Z]`, ignore case one character at a
+/// time, so that `[ß]` does not match `SS`.
+///
+/// [`Pattern`]: struct.Pattern.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CaseFold {
+    /// Compare case-sensitively.
+    #[default]
+    Sensitive,
+    /// Ignore the case of ASCII letters only, so that `README.md` matches
+    /// `readme.md` but `É` does not match `é`.
+    Ascii,
+    /// Ignore case using full Unicode case folding, so that `STRASSE`
+    /// matches `straße`.
+    Unicode,
+}
+
+/// A Unicode normalization form to which names are converted before
+/// comparison.
+///
+/// An accented letter may be stored either precomposed or as a base
+/// letter followed by a combining accent; macOS, for instance, tends to
+/// produce the latter. Normalizing both the pattern and the name to the
+/// same form allows either spelling to match. Either form suffices for
+/// matching; they differ only in what regular expressions and globs see.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Normalization {
+    /// Compare names as they are stored.
+    #[default]
+    None,
+    /// Normalization Form C, which composes accents onto their letters.
+    Nfc,
+    /// Normalization Form D, which decomposes accented letters.
+    Nfd,
+}
+
+#[derive(Clone, Debug)]
+enum Kind {
+    Any,
+    Exact {
+        target: OsString,
+        // The bytes of the target after case folding and normalization.
+        folded: Vec<u8>,
+    },
+    Names {
+        names: Vec<OsString>,
+        // Maps each folded name to its position in `names`.
+        folded: HashMap<Vec<u8>, usize>,
+    },
+    Regex {
+        source: String,
+        re: Regex,
+    },
+    Glob {
+        source: String,
+        matcher: GlobMatcher,
+        // The leading components of the glob which contain no wildcards,
+        // folded for comparison with the components of a path.
+        literal_prefix: Vec<Vec<u8>>,
+    },
+}
+
+/// Describes which entries a [`Finder`] should yield.
+///
+/// A pattern is compiled once, when it is constructed, and then tested
+/// against every entry visited by the search.
+///
+/// Names are compared as bytes, so that names which are not valid UTF-8,
+/// such as Latin-1 names on older volumes, can be matched exactly. On
+/// Unix these are the raw bytes of the name; on other platforms, names
+/// are converted to UTF-8, replacing any unpaired surrogates.
+///
+/// [`Finder`]: struct.Finder.html
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    kind: Kind,
+    match_on: MatchOn,
+    case_fold: CaseFold,
+    normalization: Normalization,
+}
+
+impl Pattern {
+    fn new(kind: Kind, match_on: MatchOn) -> Self {
+        Pattern {
+            kind,
+            match_on,
+            case_fold: CaseFold::default(),
+            normalization: Normalization::default(),
+        }
+    }
+
+    /// Creates a pattern which matches every entry.
+    pub fn any() -> Self {
+        Self::new(Kind::Any, MatchOn::default())
+    }
+
+    /// Creates a pattern which matches names exactly equal to `target`.
+    pub fn exact<S: AsRef<OsStr>>(target: S) -> Self {
+        let target = target.as_ref();
+        let kind = Kind::Exact {
+            target: target.to_os_string(),
+            folded: name_bytes(target).into_owned(),
+        };
+        Self::new(kind, MatchOn::default())
+    }
+
+    /// Creates a pattern which matches names exactly equal to any of
+    /// `names`.
+    ///
+    /// Lookups take constant time regardless of the number of names, so
+    /// searching for hundreds of names at once costs little more than
+    /// searching for one. Each match records the position of the name it
+    /// matched within `names`; see [`Finder::matches`].
+    ///
+    /// [`Finder::matches`]: struct.Finder.html#method.matches
+    pub fn names<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let names: Vec<OsString> = names
+            .into_iter()
+            .map(|name| name.as_ref().to_os_string())
+            .collect();
+        let mut folded = HashMap::new();
+        for (index, name) in names.iter().enumerate() {
+            folded.entry(name_bytes(name).into_owned()).or_insert(index);
+        }
+        Self::new(Kind::Names { names, folded }, MatchOn::default())
+    }
+
+    /// Creates a pattern from a regular expression.
+    ///
+    /// The expression is unanchored: `log` matches `build.log`. Use `^` and
+    /// `$` to require the expression to match the entire name.
+    ///
+    /// Since names are matched as bytes, `.` and other Unicode classes do
+    /// not match bytes which are not valid UTF-8. Such bytes can be matched
+    /// by disabling Unicode mode, as in `(?-u:\xE9)` or `(?-u:.)`.
+    pub fn regex(re: &str) -> Result<Self, Error> {
+        let kind = Kind::Regex {
+            source: re.to_string(),
+            re: Regex::new(re).map_err(|err| Error::invalid_pattern(re, err))?,
+        };
+        Ok(Self::new(kind, MatchOn::default()))
+    }
+
+    /// Creates a pattern from a shell glob.
+    ///
+    /// The usual shell syntax is supported: `*` and `?` match within a
+    /// single path component, `[a-z]` and `[!a-z]` match character
+    /// classes, `{foo,bar}` matches either alternative, and `**` matches
+    /// any number of directories.
+    ///
+    /// Globs which contain a `/` are matched against the path relative to
+    /// the root of the search; all others are matched against the file
+    /// name. For path globs, directories which cannot contain a match
+    /// (because they diverge from the literal leading components of the
+    /// glob, such as `src` in `src/**/mod.rs`) are not descended into.
+    pub fn glob(glob: &str) -> Result<Self, Error> {
+        let matcher = GlobBuilder::new(glob)
+            .literal_separator(true)
+            .backslash_escape(true)
+            .build()
+            .map_err(|err| Error::invalid_pattern(glob, err))?
+            .compile_matcher();
+        let literal_prefix = glob
+            .split('/')
+            .take_while(|component| !component.contains(is_glob_meta))
+            .map(|component| component.as_bytes().to_vec())
+            .collect();
+        let match_on = if glob.contains('/') {
+            MatchOn::RelativePath
+        } else {
+            MatchOn::FileName
+        };
+        let kind = Kind::Glob {
+            source: glob.to_string(),
+            matcher,
+            literal_prefix,
+        };
+        Ok(Self::new(kind, match_on))
+    }
+
+    /// Sets which portion of the path the pattern is tested against.
+    ///
+    /// Defaults to [`MatchOn::FileName`], except for globs containing a
+    /// `/`, which default to [`MatchOn::RelativePath`].
+    ///
+    /// [`MatchOn::FileName`]: enum.MatchOn.html#variant.FileName
+    /// [`MatchOn::RelativePath`]: enum.MatchOn.html#variant.RelativePath
+    pub fn match_on(mut self, match_on: MatchOn) -> Self {
+        self.match_on = match_on;
+        self
+    }
+
+    /// Sets how letter case is treated. Defaults to
+    /// [`CaseFold::Sensitive`].
+    ///
+    /// [`CaseFold::Sensitive`]: enum.CaseFold.html#variant.Sensitive
+    pub fn case_fold(mut self, case_fold: CaseFold) -> Self {
+        self.case_fold = case_fold;
+        self.recompile();
+        self
+    }
+
+    /// Normalizes both the pattern and each name to `form` before they are
+    /// compared. Defaults to [`Normalization::None`].
+    ///
+    /// [`Normalization::None`]: enum.Normalization.html#variant.None
+    pub fn normalize(mut self, form: Normalization) -> Self {
+        self.normalization = form;
+        self.recompile();
+        self
+    }
+
+    /// Rebuilds the compiled form of the pattern after its options change.
+    fn recompile(&mut self) {
+        let kind = match &self.kind {
+            Kind::Any => Kind::Any,
+            Kind::Exact { target, .. } => Kind::Exact {
+                target: target.clone(),
+                folded: self.fold(&name_bytes(target)).into_owned(),
+            },
+            Kind::Names { names, .. } => {
+                let mut folded = HashMap::new();
+                for (index, name) in names.iter().enumerate() {
+                    folded
+                        .entry(self.fold(&name_bytes(name)).into_owned())
+                        .or_insert(index);
+                }
+                Kind::Names {
+                    names: names.clone(),
+                    folded,
+                }
+            }
+            // The source was already validated when the pattern was
+            // created, and neither option alters its syntax.
+            Kind::Regex { source, .. } => Kind::Regex {
+                source: source.clone(),
+                re: compile_regex(&normalize(source, self.normalization), self.case_fold),
+            },
+            // Globs only ignore the case of ASCII letters, so for Unicode
+            // folding the glob is folded in full, as are names.
+            Kind::Glob { source, .. } => Kind::Glob {
+                source: source.clone(),
+                matcher: GlobBuilder::new(&self.fold_str(source))
+                    .literal_separator(true)
+                    .backslash_escape(true)
+                    .case_insensitive(self.case_fold != CaseFold::Sensitive)
+                    .build()
+                    .expect("previously valid glob failed to compile")
+                    .compile_matcher(),
+                literal_prefix: source
+                    .split('/')
+                    .take_while(|component| !component.contains(is_glob_meta))
+                    .map(|component| self.fold(component.as_bytes()).into_owned())
+                    .collect(),
+            },
+        };
+        self.kind = kind;
+    }
+
+    /// Converts `name` to the configured normalization form.
+    fn normalized<'a>(&self, name: &'a [u8]) -> Cow<'a, [u8]> {
+        match self.normalization {
+            Normalization::None => Cow::Borrowed(name),
+            form => map_utf8(name, |s| normalize(s, form)),
+        }
+    }
+
+    /// Applies case folding and then normalization to `name`, for
+    /// comparison against a target which was folded in the same way.
+    fn fold<'a>(&self, name: &'a [u8]) -> Cow<'a, [u8]> {
+        let folded = fold_case(name, self.case_fold);
+        match self.normalized(&folded) {
+            Cow::Borrowed(_) => folded,
+            Cow::Owned(normalized) => Cow::Owned(normalized),
+        }
+    }
+
+    /// Folds the source of a glob as names are folded, only fully folding
+    /// case, since the glob itself ignores the case of ASCII letters.
+    fn fold_str(&self, source: &str) -> String {
+        let folded = match self.case_fold {
+            CaseFold::Unicode => fold_case(source.as_bytes(), CaseFold::Unicode),
+            _ => Cow::Borrowed(source.as_bytes()),
+        };
+        let folded = String::from_utf8_lossy(&folded);
+        normalize(&folded, self.normalization).into_owned()
+    }
+
+    pub(crate) fn matches(&self, entry: &DirEntry) -> bool {
+        self.matched_target(entry).is_some()
+    }
+
+    /// Returns the position of the target which `entry` matched, if any.
+    ///
+    /// Only patterns created with [`Pattern::names`] have more than one
+    /// target; all other patterns report a position of zero.
+    pub(crate) fn matched_target(&self, entry: &DirEntry) -> Option<usize> {
+        if let Kind::Any = self.kind {
+            return Some(0);
+        }
+        let subject = match self.match_on {
+            MatchOn::FileName => name_bytes(entry.path().file_name()?),
+            MatchOn::RelativePath => {
+                let mut path = Vec::new();
+                for (i, component) in relative_components(entry).into_iter().enumerate() {
+                    if i > 0 {
+                        path.push(b'/');
+                    }
+                    path.extend_from_slice(&name_bytes(component));
+                }
+                Cow::Owned(path)
+            }
+        };
+        let matched = match &self.kind {
+            Kind::Any => true,
+            Kind::Exact { folded, .. } => *self.fold(&subject) == **folded,
+            Kind::Names { folded, .. } => return folded.get(&*self.fold(&subject)).copied(),
+            Kind::Regex { re, .. } => re.is_match(&self.fold(&subject)),
+            Kind::Glob { matcher, .. } => matcher.is_match(bytes_path(&self.fold(&subject))),
+        };
+        if matched {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    /// Returns false if no entry below the directory `entry` can match.
+    pub(crate) fn may_match_below(&self, entry: &DirEntry) -> bool {
+        match (&self.kind, self.match_on) {
+            (Kind::Glob { literal_prefix, .. }, MatchOn::RelativePath) => {
+                // Entries below `entry` can only match if its path and the
+                // literal prefix agree up to the shorter of the two.
+                relative_components(entry)
+                    .iter()
+                    .zip(literal_prefix)
+                    .all(|(component, literal)| *self.fold(&name_bytes(component)) == **literal)
+            }
+            _ => true,
+        }
+    }
+}
+
+fn is_glob_meta(c: char) -> bool {
+    matches!(c, '*' | '?' | '[' | ']' | '{' | '}' | '\\')
+}
+
+/// Returns the components of the path of `entry` below the root of the walk.
+///
+/// Since each path is built by joining names onto the root, the last
+/// `depth` components are exactly the portion below the root.
+pub(crate) fn relative_components(entry: &DirEntry) -> Vec<&OsStr> {
+    let components: Vec<_> = entry.path().components().collect();
+    components[components.len().saturating_sub(entry.depth())..]
+        .iter()
+        .map(|c| c.as_os_str())
+        .collect()
+}
+
+/// Returns the bytes which patterns match against for `name`.
+#[cfg(unix)]
+pub(crate) fn name_bytes(name: &OsStr) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+
+    Cow::Borrowed(name.as_bytes())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn name_bytes(name: &OsStr) -> Cow<'_, [u8]> {
+    match name.to_string_lossy() {
+        Cow::Borrowed(name) => Cow::Borrowed(name.as_bytes()),
+        Cow::Owned(name) => Cow::Owned(name.into_bytes()),
+    }
+}
+
+/// Converts bytes produced by `name_bytes` back into a path.
+#[cfg(unix)]
+fn bytes_path(bytes: &[u8]) -> Cow<'_, Path> {
+    use std::os::unix::ffi::OsStrExt;
+
+    Cow::Borrowed(Path::new(OsStr::from_bytes(bytes)))
+}
+
+#[cfg(not(unix))]
+fn bytes_path(bytes: &[u8]) -> Cow<'_, Path> {
+    Cow::Owned(String::from_utf8_lossy(bytes).into_owned().into())
+}
+
+/// Folds the case of `name` as `case_fold` asks.
+pub(crate) fn fold_case(name: &[u8], case_fold: CaseFold) -> Cow<'_, [u8]> {
+    match case_fold {
+        CaseFold::Sensitive => Cow::Borrowed(name),
+        CaseFold::Ascii if !name.iter().any(u8::is_ascii_uppercase) => Cow::Borrowed(name),
+        CaseFold::Ascii => Cow::Owned(name.to_ascii_lowercase()),
+        // Upper-casing expands letters such as `ß` to their full folded
+        // form, and lower-casing first brings `ẞ` along with them.
+        CaseFold::Unicode => map_utf8(name, |s| {
+            Cow::Owned(s.to_lowercase().to_uppercase().to_lowercase())
+        }),
+    }
+}
+
+/// Compiles the previously validated regular expression `source`, ignoring
+/// case as `case_fold` asks.
+///
+/// The regex engine only ignores case by simple Unicode folding. For
+/// [`CaseFold::Ascii`], ASCII letters are instead given their other case
+/// by hand, and for [`CaseFold::Unicode`], literals are folded in full, so
+/// the text searched must be folded in full too.
+///
+/// [`CaseFold::Ascii`]: enum.CaseFold.html#variant.Ascii
+/// [`CaseFold::Unicode`]: enum.CaseFold.html#variant.Unicode
+pub(crate) fn compile_regex(source: &str, case_fold: CaseFold) -> Regex {
+    let source = match case_fold {
+        CaseFold::Sensitive => Cow::Borrowed(source),
+        _ => {
+            let hir = ParserBuilder::new()
+                .utf8(false)
+                .build()
+                .parse(source)
+                .expect("previously valid regex failed to parse");
+            Cow::Owned(fold_hir(hir, case_fold).to_string())
+        }
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(case_fold == CaseFold::Unicode)
+        .build()
+        .expect("previously valid regex failed to compile")
+}
+
+/// Rewrites a regular expression to ignore case as `case_fold` asks, as
+/// described by [`compile_regex`].
+///
+/// [`compile_regex`]: fn.compile_regex.html
+fn fold_hir(hir: Hir, case_fold: CaseFold) -> Hir {
+    let fold = |hir| fold_hir(hir, case_fold);
+    match hir.into_kind() {
+        HirKind::Literal(Literal(bytes)) if case_fold == CaseFold::Unicode => {
+            Hir::literal(fold_case(&bytes, case_fold).into_owned())
+        }
+        HirKind::Literal(Literal(bytes)) => {
+            let mut hirs = Vec::new();
+            let mut literal = Vec::new();
+            for &b in bytes.iter() {
+                if !b.is_ascii_alphabetic() {
+                    literal.push(b);
+                    continue;
+                }
+                hirs.push(Hir::literal(std::mem::take(&mut literal)));
+                let cases = [b.to_ascii_lowercase(), b.to_ascii_uppercase()]
+                    .map(|c| ClassUnicodeRange::new(char::from(c), char::from(c)));
+                hirs.push(Hir::class(Class::Unicode(ClassUnicode::new(cases))));
+            }
+            hirs.push(Hir::literal(literal));
+            Hir::concat(hirs)
+        }
+        HirKind::Class(Class::Unicode(mut class)) if case_fold == CaseFold::Ascii => {
+            let mut other = Vec::new();
+            for range in class.iter() {
+                for letters in [b'a'..=b'z', b'A'..=b'Z'] {
+                    let start = (range.start() as u32).max(u32::from(*letters.start()));
+                    let end = (range.end() as u32).min(u32::from(*letters.end()));
+                    if start <= end {
+                        // Flipping this bit swaps the case of an ASCII letter.
+                        let swap = |c: u32| char::from(c as u8 ^ 0x20);
+                        other.push(ClassUnicodeRange::new(swap(start), swap(end)));
+                    }
+                }
+            }
+            class.union(&ClassUnicode::new(other));
+            Hir::class(Class::Unicode(class))
+        }
+        HirKind::Class(Class::Bytes(mut class)) if case_fold == CaseFold::Ascii => {
+            // Byte classes only ever fold ASCII letters.
+            class.case_fold_simple();
+            Hir::class(Class::Bytes(class))
+        }
+        HirKind::Class(class) => Hir::class(class),
+        HirKind::Repetition(rep) => Hir::repetition(Repetition {
+            sub: Box::new(fold(*rep.sub)),
+            ..rep
+        }),
+        HirKind::Capture(capture) => Hir::capture(Capture {
+            sub: Box::new(fold(*capture.sub)),
+            ..capture
+        }),
+        HirKind::Concat(hirs) => Hir::concat(hirs.into_iter().map(fold).collect()),
+        HirKind::Alternation(hirs) => Hir::alternation(hirs.into_iter().map(fold).collect()),
+        HirKind::Empty => Hir::empty(),
+        HirKind::Look(look) => Hir::look(look),
+    }
+}
+
+/// Converts `s` to the normalization form `form`.
+fn normalize(s: &str, form: Normalization) -> Cow<'_, str> {
+    match form {
+        Normalization::None => Cow::Borrowed(s),
+        Normalization::Nfc if is_nfc_quick(s.chars()) == IsNormalized::Yes => Cow::Borrowed(s),
+        Normalization::Nfd if is_nfd_quick(s.chars()) == IsNormalized::Yes => Cow::Borrowed(s),
+        Normalization::Nfc => Cow::Owned(s.nfc().collect()),
+        Normalization::Nfd => Cow::Owned(s.nfd().collect()),
+    }
+}
+
+/// Applies `f` to each run of valid UTF-8 within `bytes`, leaving any other
+/// bytes as they are.
+fn map_utf8<F>(bytes: &[u8], f: F) -> Cow<'_, [u8]>
+where
+    F: for<'s> Fn(&'s str) -> Cow<'s, str>,
+{
+    if let Ok(s) = std::str::from_utf8(bytes) {
+        return match f(s) {
+            Cow::Borrowed(_) => Cow::Borrowed(bytes),
+            Cow::Owned(s) => Cow::Owned(s.into_bytes()),
+        };
+    }
+    let mut mapped = Vec::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        mapped.extend_from_slice(f(chunk.valid()).as_bytes());
+        mapped.extend_from_slice(chunk.invalid());
+    }
+    Cow::Owned(mapped)
+}
@@ -0,0 +1,471 @@
+use crate::ignores::Ignores;
+use crate::matcher::io_error;
+use crate::sort::sort_entries;
+use crate::walk::{subtree_root, FileId, WalkOptions};
+use crate::{DirEntry, Error, Query, Result};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::{self, FileType};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A search which reads directories itself, one at a time, rather than
+/// through walkdir, so that directories may be read in any order: by a
+/// pool of threads, or breadth-first.
+///
+/// Unlike a walk, the search matches, counts and deduplicates entries
+/// itself, and only reports matches and errors. It may be shared between
+/// threads, each reading different directories.
+pub(crate) struct Search {
+    pub(crate) roots: Vec<PathBuf>,
+    query: Query,
+    pub(crate) options: WalkOptions,
+    seen: Option<Mutex<HashSet<FileId>>>,
+    limits: Limits,
+    visited: AtomicUsize,
+    results: AtomicUsize,
+    // The number of matches found below each entry at the depth of the
+    // limit on matches per subtree.
+    subtrees: Mutex<HashMap<PathBuf, usize>>,
+    // The depth of the shallowest match so far, when stopping at the
+    // shallowest level with any match.
+    shallowest: AtomicUsize,
+    pub(crate) quit: AtomicBool,
+}
+
+/// Bounds on how much a search visits and yields.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Limits {
+    pub(crate) visited: Option<usize>,
+    pub(crate) results: Option<usize>,
+    pub(crate) per_dir: Option<usize>,
+    // The depth of the entries below which matches are limited, and the
+    // limit.
+    pub(crate) per_subtree: Option<(usize, usize)>,
+}
+
+/// A directory waiting to be read, along with the state inherited from the
+/// directories above it.
+pub(crate) struct Task {
+    root: usize,
+    dir: DirEntry,
+    ignores: Option<Ignores>,
+    ancestors: Option<Arc<Ancestor>>,
+    device: Option<u64>,
+}
+
+/// A directory above the one being read, kept while following links to
+/// detect links which point back to an ancestor.
+struct Ancestor {
+    path: PathBuf,
+    id: FileId,
+    parent: Option<Arc<Ancestor>>,
+}
+
+impl Search {
+    pub(crate) fn new(
+        roots: Vec<PathBuf>,
+        query: Query,
+        options: WalkOptions,
+        dedup: bool,
+        limits: Limits,
+    ) -> Self {
+        Search {
+            roots,
+            query,
+            options,
+            seen: if dedup {
+                Some(Mutex::new(HashSet::new()))
+            } else {
+                None
+            },
+            limits,
+            visited: AtomicUsize::new(0),
+            results: AtomicUsize::new(0),
+            subtrees: Mutex::new(HashMap::new()),
+            shallowest: AtomicUsize::new(usize::MAX),
+            quit: AtomicBool::new(false),
+        }
+    }
+
+    /// Visits the root at `path`, returning the task to read it if it is a
+    /// directory which should be searched.
+    pub(crate) fn start<F>(&self, root: usize, path: &Path, sink: &mut F) -> Option<Task>
+    where
+        F: FnMut(usize, Result<DirEntry>) -> bool,
+    {
+        let io_error = |err| Error::Io {
+            path: Some(path.to_path_buf()),
+            depth: Some(0),
+            source: err,
+        };
+        let started = fs::symlink_metadata(path).and_then(|md| {
+            if !md.file_type().is_symlink() {
+                let entry = DirEntry::new(path.to_path_buf(), md.file_type(), false, 0);
+                return Ok((entry, md.is_dir()));
+            }
+            // A root which is a link is always searched, but only described
+            // as its target when following links.
+            let target = fs::metadata(path)?;
+            let entry = if self.options.follow_links {
+                DirEntry::new(path.to_path_buf(), target.file_type(), true, 0)
+            } else {
+                DirEntry::new(path.to_path_buf(), md.file_type(), false, 0)
+            };
+            Ok((entry, target.is_dir()))
+        });
+        let (entry, is_dir) = match started {
+            Ok(started) => started,
+            Err(err) => {
+                self.emit(root, Err(io_error(err)), sink);
+                return None;
+            }
+        };
+        let device = if self.options.same_file_system {
+            match device_num(path) {
+                Ok(device) => Some(device),
+                Err(err) => {
+                    self.emit(root, Err(io_error(err)), sink);
+                    return None;
+                }
+            }
+        } else {
+            None
+        };
+        let dir = self.visit(root, entry, is_dir, sink).0?;
+        Some(Task {
+            root,
+            dir,
+            ignores: if self.options.ignore_files {
+                Some(Ignores::new(path))
+            } else {
+                None
+            },
+            ancestors: None,
+            device,
+        })
+    }
+
+    /// Reads the directory of `task`, visiting each of its entries and
+    /// passing those subdirectories which should be searched to `queue`.
+    pub(crate) fn read_dir<Q, F>(&self, task: Task, queue: &mut Q, sink: &mut F)
+    where
+        Q: FnMut(Task),
+        F: FnMut(usize, Result<DirEntry>) -> bool,
+    {
+        let Task {
+            root,
+            dir,
+            mut ignores,
+            ancestors,
+            device,
+        } = task;
+        if dir.depth() >= self.shallowest.load(Ordering::SeqCst) || self.subtree_full(&dir) {
+            return;
+        }
+        let read = match fs::read_dir(dir.path()) {
+            Ok(read) => read,
+            Err(err) => return self.emit(root, Err(io_error(&dir, err)), sink),
+        };
+        let ancestors = if self.options.follow_links {
+            Some(Arc::new(Ancestor {
+                path: dir.path().to_path_buf(),
+                id: FileId::of(dir.path()),
+                parent: ancestors,
+            }))
+        } else {
+            None
+        };
+        let depth = dir.depth() + 1;
+        let mut entries = Vec::new();
+        for child in read {
+            let entry = child
+                .map_err(|err| io_error(&dir, err))
+                .and_then(|child| self.entry(child.path(), child.file_type(), depth, &ancestors));
+            match entry {
+                Ok(entry) => entries.push(entry),
+                Err(err) => self.emit(root, Err(err), sink),
+            }
+        }
+        if let Some(key) = self.options.sort {
+            entries = sort_entries(entries, key);
+        }
+        let mut matched = 0;
+        for entry in entries {
+            if self.quit.load(Ordering::Relaxed) {
+                return;
+            }
+            // The rest of a directory or subtree which has yielded enough
+            // matches is skipped, including its subdirectories, though not
+            // those already queued.
+            if self.limits.per_dir.is_some_and(|limit| matched >= limit) || self.subtree_full(&dir)
+            {
+                return;
+            }
+            if ignores.as_mut().is_some_and(|i| i.is_ignored(&entry)) {
+                continue;
+            }
+            let mut is_dir = entry.file_type().is_dir();
+            if let (true, Some(device)) = (is_dir, device) {
+                match device_num(entry.path()) {
+                    Ok(other) => is_dir = other == device,
+                    Err(err) => {
+                        self.emit(root, Err(io_error(&entry, err)), sink);
+                        is_dir = false;
+                    }
+                }
+            }
+            let (subdir, is_match) = self.visit(root, entry, is_dir, sink);
+            matched += usize::from(is_match);
+            if let Some(subdir) = subdir {
+                queue(Task {
+                    root,
+                    dir: subdir,
+                    ignores: ignores.clone(),
+                    ancestors: ancestors.clone(),
+                    device,
+                });
+            }
+        }
+    }
+
+    /// Describes the entry at `path`, following it if it is a link which
+    /// should be followed.
+    fn entry(
+        &self,
+        path: PathBuf,
+        file_type: io::Result<FileType>,
+        depth: usize,
+        ancestors: &Option<Arc<Ancestor>>,
+    ) -> Result<DirEntry> {
+        let io_error = |path: &Path, err| Error::Io {
+            path: Some(path.to_path_buf()),
+            depth: Some(depth),
+            source: err,
+        };
+        let file_type = file_type.map_err(|err| io_error(&path, err))?;
+        if !(self.options.follow_links && file_type.is_symlink()) {
+            return Ok(DirEntry::new(path, file_type, false, depth));
+        }
+        let target = fs::metadata(&path).map_err(|err| io_error(&path, err))?;
+        if target.is_dir() {
+            let id = FileId::of(&path);
+            let mut ancestor = ancestors.as_deref();
+            while let Some(a) = ancestor {
+                if a.id == id {
+                    return Err(Error::Loop {
+                        ancestor: a.path.clone(),
+                        child: path,
+                        depth,
+                    });
+                }
+                ancestor = a.parent.as_deref();
+            }
+        }
+        Ok(DirEntry::new(path, target.file_type(), true, depth))
+    }
+
+    /// Yields `entry` if it matches, returning it again if it is a
+    /// directory whose contents should be searched, and whether it was
+    /// yielded.
+    ///
+    /// As in a walk, entries above the minimum depth are neither counted nor
+    /// matched, though directories among them may still be pruned.
+    fn visit<F>(
+        &self,
+        root: usize,
+        entry: DirEntry,
+        is_dir: bool,
+        sink: &mut F,
+    ) -> (Option<DirEntry>, bool)
+    where
+        F: FnMut(usize, Result<DirEntry>) -> bool,
+    {
+        let descend = is_dir && entry.depth() < self.options.max_depth;
+        if entry.depth() < self.options.min_depth {
+            let subdir = match descend {
+                true => match self.query.prunes(&entry) {
+                    Ok(pruned) => Some(entry).filter(|_| !pruned),
+                    Err(err) => {
+                        self.emit(root, Err(err), sink);
+                        Some(entry)
+                    }
+                },
+                false => None,
+            };
+            return (subdir, false);
+        }
+        if let Some(limit) = self.limits.visited {
+            if self.visited.fetch_add(1, Ordering::SeqCst) >= limit {
+                if !self.quit.swap(true, Ordering::SeqCst) {
+                    let err = Error::BudgetExceeded {
+                        limit,
+                        depth: entry.depth(),
+                        path: entry.into_path(),
+                    };
+                    sink(root, Err(err));
+                }
+                return (None, false);
+            }
+        }
+        let (pruned, prune_err) = match is_dir {
+            true => match self.query.prunes(&entry) {
+                Ok(pruned) => (pruned, None),
+                Err(err) => (false, Some(err)),
+            },
+            false => (false, None),
+        };
+        let subdir = if descend && !pruned {
+            Some(entry.clone())
+        } else {
+            None
+        };
+        let mut is_match = false;
+        match self.query.matches(&entry) {
+            Ok(true) if self.is_new(&entry) && self.take_subtree(&entry) && self.take_result() => {
+                is_match = true;
+                if self.options.stop_at_shallowest {
+                    self.shallowest.fetch_min(entry.depth(), Ordering::SeqCst);
+                }
+                self.emit(root, Ok(entry), sink);
+                let limit = self.limits.results;
+                if limit.is_some_and(|limit| self.results.load(Ordering::SeqCst) >= limit) {
+                    self.quit.store(true, Ordering::SeqCst);
+                }
+            }
+            Ok(_) => {}
+            Err(err) => self.emit(root, Err(err), sink),
+        }
+        if let Some(err) = prune_err {
+            self.emit(root, Err(err), sink);
+        }
+        (subdir, is_match)
+    }
+
+    /// Counts a match against the limit on results, returning false, and
+    /// stopping the search, if the limit has already been reached.
+    fn take_result(&self) -> bool {
+        let taken = self
+            .limits
+            .results
+            .is_none_or(|limit| self.results.fetch_add(1, Ordering::SeqCst) < limit);
+        if !taken {
+            self.quit.store(true, Ordering::SeqCst);
+        }
+        taken
+    }
+
+    /// Counts a match against the limit on matches in its subtree,
+    /// returning false if the limit has already been reached.
+    fn take_subtree(&self, entry: &DirEntry) -> bool {
+        let (depth, limit) = match self.limits.per_subtree {
+            Some(per_subtree) => per_subtree,
+            None => return true,
+        };
+        let root = match subtree_root(entry, depth) {
+            Some(root) => root,
+            None => return true,
+        };
+        let mut subtrees = self.subtrees.lock().unwrap_or_else(|e| e.into_inner());
+        let count = subtrees.entry(root.to_path_buf()).or_insert(0);
+        *count += 1;
+        *count <= limit
+    }
+
+    /// Returns true if the subtree containing the contents of `dir` has
+    /// already yielded as many matches as are wanted from it.
+    fn subtree_full(&self, dir: &DirEntry) -> bool {
+        let (depth, limit) = match self.limits.per_subtree {
+            Some(per_subtree) if dir.depth() >= per_subtree.0 => per_subtree,
+            _ => return false,
+        };
+        let root = subtree_root(dir, depth).unwrap_or_else(|| dir.path());
+        let subtrees = self.subtrees.lock().unwrap_or_else(|e| e.into_inner());
+        subtrees.get(root).is_some_and(|&count| count >= limit)
+    }
+
+    /// Returns false if `entry` has already been yielded while
+    /// deduplicating.
+    fn is_new(&self, entry: &DirEntry) -> bool {
+        self.seen.as_ref().is_none_or(|seen| {
+            let id = FileId::of(entry.path());
+            seen.lock().unwrap_or_else(|e| e.into_inner()).insert(id)
+        })
+    }
+
+    /// Passes `item` to `sink`, stopping the search if it is refused.
+    fn emit<F>(&self, root: usize, item: Result<DirEntry>, sink: &mut F)
+    where
+        F: FnMut(usize, Result<DirEntry>) -> bool,
+    {
+        if !self.quit.load(Ordering::Relaxed) && !sink(root, item) {
+            self.quit.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn device_num(path: &Path) -> io::Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    fs::metadata(path).map(|md| md.dev())
+}
+
+#[cfg(not(unix))]
+fn device_num(_: &Path) -> io::Result<u64> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "breadth-first and parallel searches cannot stay on one filesystem on this platform",
+    ))
+}
+
+/// A breadth-first search, which reads directories in order of depth, so
+/// that every match at one depth is yielded before any match below it.
+pub(crate) struct BreadthFirst {
+    search: Search,
+    queue: VecDeque<Task>,
+    found: VecDeque<Result<DirEntry>>,
+    started: bool,
+}
+
+impl BreadthFirst {
+    pub(crate) fn new(search: Search) -> Self {
+        BreadthFirst {
+            search,
+            queue: VecDeque::new(),
+            found: VecDeque::new(),
+            started: false,
+        }
+    }
+}
+
+impl Iterator for BreadthFirst {
+    type Item = Result<DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (search, queue, found) = (&self.search, &mut self.queue, &mut self.found);
+        if !self.started {
+            self.started = true;
+            if !search.options.is_empty() {
+                for (root, path) in search.roots.iter().enumerate() {
+                    let task = search.start(root, path, &mut |_, item| {
+                        found.push_back(item);
+                        true
+                    });
+                    queue.extend(task);
+                }
+            }
+        }
+        // Reading a directory may find nothing, so read until something
+        // is found or nothing remains.
+        while found.is_empty() && !search.quit.load(Ordering::Relaxed) {
+            let task = queue.pop_front()?;
+            search.read_dir(task, &mut |task| queue.push_back(task), &mut |_, item| {
+                found.push_back(item);
+                true
+            });
+        }
+        found.pop_front()
+    }
+}
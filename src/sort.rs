@@ -0,0 +1,240 @@
+use crate::pattern::name_bytes;
+use crate::DirEntry;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A way of ordering entries, used by [`Finder::sort_by`] to order the
+/// contents of each directory, and by [`Finder::sort_results`] to order
+/// every result of a search.
+///
+/// Entries which are equal by the chosen key are ordered by path, so that
+/// the same entries are always sorted the same way. Entries whose metadata
+/// cannot be read sort as though they had no size or modification time,
+/// ahead of all others.
+///
+/// [`Finder::sort_by`]: struct.Finder.html#method.sort_by
+/// [`Finder::sort_results`]: struct.Finder.html#method.sort_results
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    /// By file name, comparing bytes, so that `B` sorts before `a`.
+    Name,
+    /// By file name, comparing runs of digits by their numeric value, so
+    /// that `v2` sorts before `v10`, and `1.9.0` before `1.10.0`.
+    NaturalName,
+    /// Shallowest first. Since the entries of a directory are all at the
+    /// same depth, this orders them by name.
+    Depth,
+    /// Smallest first, by the length reported by their metadata.
+    Size,
+    /// Least recently modified first.
+    Modified,
+}
+
+/// What an entry is compared by: its path, depth and, for sort keys which
+/// need it, parts of its metadata.
+pub(crate) struct Sortable<'a> {
+    path: &'a Path,
+    name: &'a OsStr,
+    depth: usize,
+    stat: Stat,
+}
+
+/// The parts of an entry's metadata which it may be sorted by, read once
+/// per entry.
+#[derive(Clone, Copy, Debug, Default)]
+struct Stat {
+    len: Option<u64>,
+    modified: Option<SystemTime>,
+}
+
+impl Stat {
+    fn of(key: SortKey, md: impl FnOnce() -> Option<Metadata>) -> Stat {
+        if !matches!(key, SortKey::Size | SortKey::Modified) {
+            return Stat::default();
+        }
+        match md() {
+            Some(md) => Stat {
+                len: Some(md.len()),
+                modified: md.modified().ok(),
+            },
+            None => Stat::default(),
+        }
+    }
+}
+
+impl<'a> Sortable<'a> {
+    fn with_stat(entry: &'a DirEntry, stat: Stat) -> Self {
+        Sortable {
+            path: entry.path(),
+            name: entry.file_name(),
+            depth: entry.depth(),
+            stat,
+        }
+    }
+
+    fn from_walkdir(entry: &'a walkdir::DirEntry, stat: Stat) -> Self {
+        Sortable {
+            path: entry.path(),
+            name: entry.file_name(),
+            depth: entry.depth(),
+            stat,
+        }
+    }
+}
+
+impl SortKey {
+    pub(crate) fn compare(self, a: &Sortable<'_>, b: &Sortable<'_>) -> Ordering {
+        let by_key = match self {
+            SortKey::Name => name_bytes(a.name).cmp(&name_bytes(b.name)),
+            SortKey::NaturalName => natural_cmp(&name_bytes(a.name), &name_bytes(b.name)),
+            SortKey::Depth => a.depth.cmp(&b.depth),
+            SortKey::Size => a.stat.len.cmp(&b.stat.len),
+            SortKey::Modified => a.stat.modified.cmp(&b.stat.modified),
+        };
+        by_key.then_with(|| a.path.cmp(b.path))
+    }
+}
+
+/// Sorts `entries` by `key`, reading the metadata of each entry at most
+/// once.
+pub(crate) fn sort_entries(entries: Vec<DirEntry>, key: SortKey) -> Vec<DirEntry> {
+    let mut keyed: Vec<_> = entries
+        .into_iter()
+        .map(|entry| (Stat::of(key, || entry.metadata().ok()), entry))
+        .collect();
+    keyed.sort_by(|(a_stat, a), (b_stat, b)| {
+        key.compare(
+            &Sortable::with_stat(a, *a_stat),
+            &Sortable::with_stat(b, *b_stat),
+        )
+    });
+    keyed.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// Returns a comparator with which walkdir sorts the contents of each
+/// directory by `key`.
+///
+/// Walkdir only sorts with a comparator, so the metadata of each entry is
+/// kept while its directory is sorted, so as to read it at most once, as
+/// [`sort_entries`] does.
+///
+/// [`sort_entries`]: fn.sort_entries.html
+pub(crate) fn walkdir_sorter(
+    key: SortKey,
+) -> impl FnMut(&walkdir::DirEntry, &walkdir::DirEntry) -> Ordering + Send + Sync + 'static {
+    // The directory being sorted, and the metadata of its entries.
+    let mut dir = PathBuf::new();
+    let mut stats: HashMap<OsString, Stat> = HashMap::new();
+    move |a, b| {
+        if !matches!(key, SortKey::Size | SortKey::Modified) {
+            return key.compare(
+                &Sortable::from_walkdir(a, Stat::default()),
+                &Sortable::from_walkdir(b, Stat::default()),
+            );
+        }
+        let parent = a.path().parent().unwrap_or_else(|| a.path());
+        if parent != dir {
+            dir = parent.to_path_buf();
+            stats.clear();
+        }
+        let mut stat = |entry: &walkdir::DirEntry| {
+            *stats
+                .entry(entry.file_name().to_os_string())
+                .or_insert_with(|| Stat::of(key, || entry.metadata().ok()))
+        };
+        let (a_stat, b_stat) = (stat(a), stat(b));
+        key.compare(
+            &Sortable::from_walkdir(a, a_stat),
+            &Sortable::from_walkdir(b, b_stat),
+        )
+    }
+}
+
+/// Compares names, treating each run of ASCII digits as a number.
+///
+/// Numbers which are equal in value, such as `7` and `007`, are ordered by
+/// their number of leading zeros, fewest first.
+fn natural_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    let mut zeros = Ordering::Equal;
+    loop {
+        match (a.first(), b.first()) {
+            (None, None) => return zeros,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, rest_a) = split_digits(a);
+                let (y, rest_b) = split_digits(b);
+                let (x_value, y_value) = (trim_zeros(x), trim_zeros(y));
+                let by_value = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value));
+                if by_value != Ordering::Equal {
+                    return by_value;
+                }
+                zeros = zeros.then(x.len().cmp(&y.len()));
+                a = rest_a;
+                b = rest_b;
+            }
+            (Some(x), Some(y)) if x != y => return x.cmp(y),
+            _ => {
+                a = &a[1..];
+                b = &b[1..];
+            }
+        }
+    }
+}
+
+fn split_digits(s: &[u8]) -> (&[u8], &[u8]) {
+    let end = s
+        .iter()
+        .position(|c| !c.is_ascii_digit())
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let start = digits
+        .iter()
+        .position(|&c| c != b'0')
+        .unwrap_or(digits.len());
+    &digits[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn walkdir_by_size() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        for (name, len) in &[("a", 3), ("b", 1), ("c", 2), ("d", 1)] {
+            std::fs::write(tmp_dir.path().join(name), vec![b'x'; *len]).unwrap();
+        }
+        let names: Vec<_> = walkdir::WalkDir::new(tmp_dir.path())
+            .min_depth(1)
+            .sort_by(walkdir_sorter(SortKey::Size))
+            .into_iter()
+            .map(|e| e.unwrap().file_name().to_os_string())
+            .collect();
+        assert_eq!(names, vec!["b", "d", "c", "a"]);
+    }
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec![
+            "v10.txt", "v2.txt", "v1.10.0", "v1.9.0", "v02.txt", "a", "v", "B",
+        ];
+        names.sort_by(|a, b| natural_cmp(a.as_bytes(), b.as_bytes()));
+        assert_eq!(
+            names,
+            vec!["B", "a", "v", "v1.9.0", "v1.10.0", "v2.txt", "v02.txt", "v10.txt"]
+        );
+    }
+}
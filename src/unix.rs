@@ -0,0 +1,296 @@
+use crate::matcher::io_error;
+use crate::{DirEntry, Error, Matcher};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::sync::Mutex;
+
+/// The permission bits of a mode, including the setuid, setgid and sticky
+/// bits, but not the file type.
+const PERMISSION_BITS: u32 = 0o7777;
+
+/// Matches entries by their permission bits, in the manner of `find -perm`.
+///
+/// Modes are given in octal, including the setuid (`0o4000`), setgid
+/// (`0o2000`) and sticky (`0o1000`) bits. To find world-writable files and
+/// setuid binaries:
+///
+/// ```
+/// use where_is::{Matcher, Mode};
+///
+/// let suspicious = Mode::any(0o002).or(Mode::all(0o4000));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mode {
+    bits: u32,
+    test: ModeTest,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ModeTest {
+    Exact,
+    All,
+    Any,
+}
+
+impl Mode {
+    /// Matches entries whose permission bits are exactly `bits`, like
+    /// `find -perm 644`.
+    pub fn exact(bits: u32) -> Self {
+        Self::new(bits, ModeTest::Exact)
+    }
+
+    /// Matches entries with every one of `bits` set, like
+    /// `find -perm -644`.
+    pub fn all(bits: u32) -> Self {
+        Self::new(bits, ModeTest::All)
+    }
+
+    /// Matches entries with at least one of `bits` set, like
+    /// `find -perm /644`. If `bits` is zero, every entry matches.
+    pub fn any(bits: u32) -> Self {
+        Self::new(bits, ModeTest::Any)
+    }
+
+    fn new(bits: u32, test: ModeTest) -> Self {
+        Mode {
+            bits: bits & PERMISSION_BITS,
+            test,
+        }
+    }
+}
+
+impl Matcher for Mode {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        let mode = entry.metadata()?.mode() & PERMISSION_BITS;
+        Ok(match self.test {
+            ModeTest::Exact => mode == self.bits,
+            ModeTest::All => mode & self.bits == self.bits,
+            ModeTest::Any => self.bits == 0 || mode & self.bits != 0,
+        })
+    }
+}
+
+/// Matches entries owned by a user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Owner {
+    uid: u32,
+}
+
+impl Owner {
+    /// Matches entries owned by the user with id `uid`.
+    pub fn uid(uid: u32) -> Self {
+        Owner { uid }
+    }
+
+    /// Matches entries owned by the user called `name`, which is looked up
+    /// immediately.
+    ///
+    /// Fails if no such user exists.
+    pub fn name(name: &str) -> Result<Self, Error> {
+        match user_id(name) {
+            Some(uid) => Ok(Self::uid(uid)),
+            None => Err(Error::InvalidValue {
+                value: name.to_string(),
+                expected: "the name of a user",
+            }),
+        }
+    }
+}
+
+impl Matcher for Owner {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        Ok(entry.metadata()?.uid() == self.uid)
+    }
+}
+
+/// Matches entries which belong to a group.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Group {
+    gid: u32,
+}
+
+impl Group {
+    /// Matches entries which belong to the group with id `gid`.
+    pub fn gid(gid: u32) -> Self {
+        Group { gid }
+    }
+
+    /// Matches entries which belong to the group called `name`, which is
+    /// looked up immediately.
+    ///
+    /// Fails if no such group exists.
+    pub fn name(name: &str) -> Result<Self, Error> {
+        match group_id(name) {
+            Some(gid) => Ok(Self::gid(gid)),
+            None => Err(Error::InvalidValue {
+                value: name.to_string(),
+                expected: "the name of a group",
+            }),
+        }
+    }
+}
+
+impl Matcher for Group {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        Ok(entry.metadata()?.gid() == self.gid)
+    }
+}
+
+/// Matches entries whose owner or group does not exist, like
+/// `find -nouser -o -nogroup`, as often left behind when accounts are
+/// removed.
+///
+/// Each id is looked up once per matcher, and the result remembered.
+#[derive(Debug, Default)]
+pub struct NoOwner {
+    users: Mutex<HashMap<u32, bool>>,
+    groups: Mutex<HashMap<u32, bool>>,
+}
+
+impl NoOwner {
+    /// Matches entries whose owner or group does not exist.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Matcher for NoOwner {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        let md = entry.metadata()?;
+        let known = |cache: &Mutex<HashMap<u32, bool>>, id, exists: fn(u32) -> bool| {
+            let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+            *cache.entry(id).or_insert_with(|| exists(id))
+        };
+        Ok(!known(&self.users, md.uid(), user_exists)
+            || !known(&self.groups, md.gid(), group_exists))
+    }
+}
+
+/// Matches entries which the current user may read, write or execute.
+///
+/// Permissions are checked with `access(2)`, which takes supplementary
+/// groups, superuser privileges and access control lists into account,
+/// using the real rather than effective user and group ids.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    /// The entry may be read, or a directory listed.
+    Read,
+    /// The entry may be written, or a directory's contents changed.
+    Write,
+    /// The entry may be executed, or a directory searched.
+    Execute,
+}
+
+impl Matcher for Access {
+    fn is_match(&self, entry: &DirEntry) -> Result<bool, Error> {
+        let mode = match self {
+            Access::Read => libc::R_OK,
+            Access::Write => libc::W_OK,
+            Access::Execute => libc::X_OK,
+        };
+        let path = CString::new(entry.path().as_os_str().as_bytes())
+            .map_err(|err| io_error(entry, err.into()))?;
+        if unsafe { libc::access(path.as_ptr(), mode) } == 0 {
+            return Ok(true);
+        }
+        let err = std::io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::EACCES) | Some(libc::EROFS) | Some(libc::ETXTBSY) => Ok(false),
+            _ => Err(io_error(entry, err)),
+        }
+    }
+}
+
+fn user_id(name: &str) -> Option<u32> {
+    let name = CString::new(name).ok()?;
+    lookup(|entry: &mut libc::passwd, buf, result| unsafe {
+        libc::getpwnam_r(name.as_ptr(), entry, buf.as_mut_ptr(), buf.len(), result)
+    })
+    .map(|entry| entry.pw_uid)
+}
+
+fn group_id(name: &str) -> Option<u32> {
+    let name = CString::new(name).ok()?;
+    lookup(|entry: &mut libc::group, buf, result| unsafe {
+        libc::getgrnam_r(name.as_ptr(), entry, buf.as_mut_ptr(), buf.len(), result)
+    })
+    .map(|entry| entry.gr_gid)
+}
+
+fn user_exists(uid: u32) -> bool {
+    lookup(|entry: &mut libc::passwd, buf, result| unsafe {
+        libc::getpwuid_r(uid, entry, buf.as_mut_ptr(), buf.len(), result)
+    })
+    .is_some()
+}
+
+fn group_exists(gid: u32) -> bool {
+    lookup(|entry: &mut libc::group, buf, result| unsafe {
+        libc::getgrgid_r(gid, entry, buf.as_mut_ptr(), buf.len(), result)
+    })
+    .is_some()
+}
+
+/// Calls one of the reentrant `getpw*_r` or `getgr*_r` functions, growing
+/// its buffer until the entry fits. Only the fixed-size fields of the
+/// returned entry may be used, as its strings point into the buffer.
+fn lookup<T, F>(mut call: F) -> Option<T>
+where
+    F: FnMut(&mut T, &mut Vec<libc::c_char>, &mut *mut T) -> libc::c_int,
+{
+    let mut buf = vec![0; 1024];
+    loop {
+        // Both are plain C structs, for which all zeroes is a valid value.
+        let mut entry: T = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        match call(&mut entry, &mut buf, &mut result) {
+            0 if result.is_null() => return None,
+            0 => return Some(entry),
+            libc::ERANGE if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::dir_entry;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use tempdir::TempDir;
+
+    #[test]
+    fn mode_bits() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let path = tmp_dir.path().join("a");
+        fs::write(&path, "").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o4755)).unwrap();
+        let a = dir_entry(tmp_dir.path(), "a");
+
+        assert!(Mode::exact(0o4755).is_match(&a).unwrap());
+        assert!(!Mode::exact(0o755).is_match(&a).unwrap());
+        assert!(Mode::all(0o4000).is_match(&a).unwrap());
+        assert!(!Mode::all(0o4002).is_match(&a).unwrap());
+        assert!(Mode::any(0o4002).is_match(&a).unwrap());
+        assert!(!Mode::any(0o002).is_match(&a).unwrap());
+        assert!(Access::Read.is_match(&a).unwrap());
+    }
+
+    #[test]
+    fn owner_and_group() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let a = dir_entry(tmp_dir.path(), "");
+        let md = a.metadata().unwrap();
+
+        assert!(Owner::uid(md.uid()).is_match(&a).unwrap());
+        assert!(!Owner::uid(md.uid() + 1).is_match(&a).unwrap());
+        assert!(Group::gid(md.gid()).is_match(&a).unwrap());
+        assert_eq!(Owner::name("root").unwrap(), Owner::uid(0));
+        assert!(Owner::name("no such user").is_err());
+        assert!(Group::name("no such group").is_err());
+        assert!(!NoOwner::new().is_match(&a).unwrap());
+    }
+}
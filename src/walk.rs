@@ -0,0 +1,452 @@
+use crate::ignores::Ignores;
+use crate::search::BreadthFirst;
+use crate::sort::{walkdir_sorter, SortKey};
+use crate::{DirEntry, Error};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use walkdir::WalkDir;
+
+/// Traversal settings for a [`Finder`], applied to the underlying
+/// [`WalkDir`] when a search begins.
+///
+/// [`Finder`]: struct.Finder.html
+#[derive(Clone, Debug)]
+pub(crate) struct WalkOptions {
+    pub(crate) min_depth: usize,
+    pub(crate) max_depth: usize,
+    pub(crate) follow_links: bool,
+    pub(crate) same_file_system: bool,
+    pub(crate) max_open: usize,
+    pub(crate) contents_first: bool,
+    pub(crate) ignore_files: bool,
+    pub(crate) sort: Option<SortKey>,
+    pub(crate) breadth_first: bool,
+    pub(crate) stop_at_shallowest: bool,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        WalkOptions {
+            min_depth: 0,
+            max_depth: usize::MAX,
+            follow_links: false,
+            same_file_system: false,
+            // Matches the default of walkdir.
+            max_open: 10,
+            contents_first: false,
+            ignore_files: false,
+            sort: None,
+            breadth_first: false,
+            stop_at_shallowest: false,
+        }
+    }
+}
+
+impl WalkOptions {
+    /// Returns true if the depth bounds cannot admit any entry.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.min_depth > self.max_depth
+    }
+
+    pub(crate) fn walk_dir(&self, root: &Path) -> WalkDir {
+        let walk = WalkDir::new(root)
+            .max_depth(self.max_depth)
+            .follow_links(self.follow_links)
+            .same_file_system(self.same_file_system)
+            .max_open(self.max_open)
+            .contents_first(self.contents_first);
+        match self.sort {
+            Some(key) => walk.sort_by(walkdir_sorter(key)),
+            None => walk,
+        }
+    }
+
+    /// Begins the walk of `root`, along with the ignore rules to apply to
+    /// it, if any.
+    pub(crate) fn walk(&self, root: &Path) -> (walkdir::IntoIter, Option<Ignores>) {
+        let ignores = if self.ignore_files {
+            Some(Ignores::new(root))
+        } else {
+            None
+        };
+        (self.walk_dir(root).into_iter(), ignores)
+    }
+}
+
+/// The order in which a [`Finder`] with several roots yields entries.
+///
+/// [`Finder`]: struct.Finder.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RootOrder {
+    /// Search each root to completion before moving on to the next, in the
+    /// order the roots were added.
+    #[default]
+    Sequential,
+    /// Alternate between roots, visiting one entry from each in turn.
+    Interleaved,
+}
+
+/// Decides whether to skip the contents of a directory.
+pub(crate) type Prune = Box<dyn FnMut(&DirEntry) -> Result<bool, Error>>;
+
+/// Where a [`DirLimits`] asks the walk to skip the rest of a directory or
+/// subtree, once as many matches as are wanted from it have been found.
+///
+/// [`DirLimits`]: struct.DirLimits.html
+pub(crate) type SkipDir = Rc<Cell<Option<Skip>>>;
+
+/// The remaining contents of `dir` to skip, apart from those of `keep`: a
+/// matching directory whose own contents are still searched.
+pub(crate) struct Skip {
+    dir: PathBuf,
+    keep: Option<PathBuf>,
+}
+
+/// Limits the number of matches yielded from the entries of each
+/// directory, and from below each entry at a given depth.
+pub(crate) struct DirLimits {
+    per_dir: Option<usize>,
+    per_subtree: Option<(usize, usize)>,
+    dirs: HashMap<PathBuf, usize>,
+    subtrees: HashMap<PathBuf, usize>,
+    // Where to ask the walk to skip the rest of a directory once its limit
+    // is reached, if it can.
+    skip: Option<SkipDir>,
+}
+
+impl DirLimits {
+    pub(crate) fn new(
+        per_dir: Option<usize>,
+        per_subtree: Option<(usize, usize)>,
+        skip: Option<SkipDir>,
+    ) -> Self {
+        DirLimits {
+            per_dir,
+            per_subtree,
+            dirs: HashMap::new(),
+            subtrees: HashMap::new(),
+            skip,
+        }
+    }
+
+    /// Counts the match `entry` against its directory and subtree,
+    /// returning false if either has already yielded enough matches.
+    pub(crate) fn admit(&mut self, entry: &DirEntry) -> bool {
+        let dir = self
+            .per_dir
+            .and_then(|limit| Some((limit, parent_dir(entry)?)));
+        let subtree = self
+            .per_subtree
+            .and_then(|(depth, limit)| Some((limit, subtree_root(entry, depth)?)));
+        let full = |counts: &HashMap<PathBuf, usize>, (limit, key): (usize, &Path)| {
+            counts.get(key).is_some_and(|&count| count >= limit)
+        };
+        if dir.is_some_and(|d| full(&self.dirs, d))
+            || subtree.is_some_and(|t| full(&self.subtrees, t))
+        {
+            return false;
+        }
+        let mut skip = None;
+        if let Some((limit, dir)) = dir {
+            let count = self.dirs.entry(dir.to_path_buf()).or_insert(0);
+            *count += 1;
+            if *count == limit {
+                let keep = Some(entry.path().to_path_buf()).filter(|_| entry.file_type().is_dir());
+                skip = Some(Skip {
+                    dir: dir.to_path_buf(),
+                    keep,
+                });
+            }
+        }
+        if let Some((limit, root)) = subtree {
+            let count = self.subtrees.entry(root.to_path_buf()).or_insert(0);
+            *count += 1;
+            // Nothing more is wanted from anywhere in the subtree, which
+            // contains the directory of the entry.
+            if *count == limit {
+                skip = Some(Skip {
+                    dir: root.to_path_buf(),
+                    keep: None,
+                });
+            }
+        }
+        if let (Some(cell), Some(skip)) = (&self.skip, skip) {
+            cell.set(Some(skip));
+        }
+        true
+    }
+}
+
+/// Returns the directory containing `entry`, unless it is a root.
+fn parent_dir(entry: &DirEntry) -> Option<&Path> {
+    entry.path().parent().filter(|_| entry.depth() > 0)
+}
+
+/// Returns the entry at `depth` above `entry`, whose subtree contains it,
+/// unless `entry` is not below that depth.
+pub(crate) fn subtree_root(entry: &DirEntry, depth: usize) -> Option<&Path> {
+    let below = entry.depth().checked_sub(depth).filter(|&n| n > 0)?;
+    entry.path().ancestors().nth(below)
+}
+
+/// The directory walk underlying a [`Finder`], which visits each of its
+/// roots, depth-first unless searching breadth-first, and skips the
+/// contents of pruned or ignored directories.
+///
+/// [`Finder`]: struct.Finder.html
+pub struct Walk {
+    inner: Inner,
+}
+
+enum Inner {
+    DepthFirst(DepthFirst),
+    BreadthFirst(Box<BreadthFirst>),
+}
+
+/// A depth-first walk of each root in turn, or of all of them at once,
+/// using walkdir.
+struct DepthFirst {
+    walks: Vec<RootWalk>,
+    order: RootOrder,
+    current: usize,
+    // The walk which yielded the previous entry.
+    last: usize,
+    prune: Prune,
+    skip: Option<SkipDir>,
+    contents_first: bool,
+    // Entries above this depth are walked, pruned and ignored, but not
+    // yielded.
+    min_depth: usize,
+    // An error raised while deciding whether to prune the previous entry,
+    // to be reported after that entry.
+    pending: Option<Error>,
+}
+
+/// The walk of a single root.
+struct RootWalk {
+    walk: walkdir::IntoIter,
+    ignores: Option<Ignores>,
+    // The directories whose remaining contents are being skipped, each
+    // inside the one before.
+    skipping: Vec<Skip>,
+    // When yielding the contents of directories first, the directories
+    // above the previous entry, and whether their contents are pruned.
+    pruned: Vec<(PathBuf, bool)>,
+}
+
+impl Walk {
+    pub(crate) fn new(
+        walks: Vec<(walkdir::IntoIter, Option<Ignores>)>,
+        order: RootOrder,
+        prune: Prune,
+        skip: Option<SkipDir>,
+        contents_first: bool,
+        min_depth: usize,
+    ) -> Self {
+        let walks = walks
+            .into_iter()
+            .map(|(walk, ignores)| RootWalk {
+                walk,
+                ignores,
+                skipping: Vec::new(),
+                pruned: Vec::new(),
+            })
+            .collect();
+        Walk {
+            inner: Inner::DepthFirst(DepthFirst {
+                walks,
+                order,
+                current: 0,
+                last: 0,
+                prune,
+                skip,
+                contents_first,
+                min_depth,
+                pending: None,
+            }),
+        }
+    }
+
+    pub(crate) fn breadth_first(search: BreadthFirst) -> Self {
+        Walk {
+            inner: Inner::BreadthFirst(Box::new(search)),
+        }
+    }
+}
+
+impl Iterator for Walk {
+    type Item = Result<DirEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            Inner::DepthFirst(walk) => walk.next(),
+            Inner::BreadthFirst(search) => search.next(),
+        }
+    }
+}
+
+impl Iterator for DepthFirst {
+    type Item = Result<DirEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // No walk has been dropped since the previous entry was yielded, so
+        // `last` still refers to the walk which yielded it.
+        if let Some(skip) = self.skip.as_ref().and_then(|skip| skip.take()) {
+            if let Some(walk) = self.walks.get_mut(self.last) {
+                walk.skipping.push(skip);
+            }
+        }
+        if let Some(err) = self.pending.take() {
+            return Some(Err(err));
+        }
+        while !self.walks.is_empty() {
+            self.current %= self.walks.len();
+            let RootWalk {
+                walk,
+                ignores,
+                skipping,
+                pruned,
+            } = &mut self.walks[self.current];
+            let result = match walk.next() {
+                Some(result) => result.map(DirEntry::from),
+                // Dropping the exhausted walk shifts its successor into
+                // `current`.
+                None => {
+                    drop(self.walks.remove(self.current));
+                    continue;
+                }
+            };
+            if let Ok(dent) = &result {
+                while skipping
+                    .last()
+                    .is_some_and(|skip| !dent.path().starts_with(&skip.dir))
+                {
+                    skipping.pop();
+                }
+                // Whether the walk has just descended into the entry or
+                // not, the directory on top of its stack is the skipped
+                // directory or one below it, and so can be skipped too.
+                if let Some(skip) = skipping.last() {
+                    if !skip
+                        .keep
+                        .as_ref()
+                        .is_some_and(|keep| dent.path().starts_with(keep))
+                    {
+                        walk.skip_current_dir();
+                        continue;
+                    }
+                }
+                // When a directory is yielded after its contents, walkdir has
+                // already left it, so skipping it would skip the rest of its
+                // parent instead.
+                if self.contents_first {
+                    let is_pruned = in_pruned_dir(pruned, &mut self.prune, dent, &mut self.pending);
+                    if is_pruned || ignores.as_mut().is_some_and(|i| i.is_ignored(dent)) {
+                        continue;
+                    }
+                } else if ignores.as_mut().is_some_and(|i| i.is_ignored(dent)) {
+                    if dent.file_type().is_dir() {
+                        walk.skip_current_dir();
+                    }
+                    continue;
+                } else if dent.file_type().is_dir() {
+                    match (self.prune)(dent) {
+                        Ok(true) => walk.skip_current_dir(),
+                        Ok(false) => {}
+                        Err(err) => self.pending = Some(err),
+                    }
+                }
+                if dent.depth() < self.min_depth {
+                    if let Some(err) = self.pending.take() {
+                        return Some(Err(err));
+                    }
+                    continue;
+                }
+            }
+            self.last = self.current;
+            if self.order == RootOrder::Interleaved {
+                self.current += 1;
+            }
+            return Some(result.map_err(Error::from));
+        }
+        None
+    }
+}
+
+/// Returns true if `entry` is below a directory whose contents are pruned,
+/// deciding whether to prune each directory above it the first time one of
+/// its contents is seen, since the directory itself is only yielded last.
+///
+/// `pruned` holds the directories above the previous entry, root first.
+fn in_pruned_dir(
+    pruned: &mut Vec<(PathBuf, bool)>,
+    prune: &mut Prune,
+    entry: &DirEntry,
+    pending: &mut Option<Error>,
+) -> bool {
+    let ancestors: Vec<&Path> = entry
+        .path()
+        .ancestors()
+        .skip(1)
+        .take(entry.depth())
+        .collect();
+    while pruned
+        .last()
+        .is_some_and(|(dir, _)| !ancestors.iter().any(|a| a == dir))
+    {
+        pruned.pop();
+    }
+    for depth in pruned.len()..entry.depth() {
+        let dir = ancestors[entry.depth() - 1 - depth];
+        let above = pruned.last().is_some_and(|&(_, is_pruned)| is_pruned);
+        let is_pruned = above || {
+            // Only directories which were searched are described, and the
+            // walk only searches links to directories it has followed.
+            let follow_link = fs::symlink_metadata(dir).is_ok_and(|md| md.file_type().is_symlink());
+            match fs::metadata(dir) {
+                Ok(md) => {
+                    let dir = DirEntry::new(dir.to_path_buf(), md.file_type(), follow_link, depth);
+                    prune(&dir).unwrap_or_else(|err| {
+                        pending.get_or_insert(err);
+                        false
+                    })
+                }
+                Err(_) => false,
+            }
+        };
+        pruned.push((dir.to_path_buf(), is_pruned));
+    }
+    pruned.last().is_some_and(|&(_, is_pruned)| is_pruned)
+}
+
+/// Identifies the file an entry refers to, after resolving symbolic links.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum FileId {
+    #[cfg(unix)]
+    Inode {
+        dev: u64,
+        ino: u64,
+    },
+    Path(PathBuf),
+}
+
+impl FileId {
+    pub(crate) fn of(path: &Path) -> FileId {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            // Fall back to the link itself if its target is missing.
+            if let Ok(md) = path.metadata().or_else(|_| path.symlink_metadata()) {
+                return FileId::Inode {
+                    dev: md.dev(),
+                    ino: md.ino(),
+                };
+            }
+        }
+        FileId::Path(path.canonicalize().unwrap_or_else(|_| path.to_path_buf()))
+    }
+}
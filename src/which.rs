@@ -0,0 +1,196 @@
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::vec;
+
+/// Locates the executable which would run for the command `name`, by
+/// searching each directory of the `PATH` environment variable in order.
+///
+/// Returns `None` if no executable is found. See [`Which`] for
+/// finding every match, or searching other directories.
+///
+/// ```no_run
+/// let cargo = where_is::which("cargo");
+/// ```
+///
+/// [`Which`]: struct.Which.html
+pub fn which<S: AsRef<OsStr>>(name: S) -> Option<PathBuf> {
+    Which::new(name).into_iter().next()
+}
+
+/// A search for executables, in the manner of the `which` command.
+///
+/// Only regular files (or symbolic links to them) which the current user
+/// may execute are considered matches. On Windows, where executability is
+/// determined by file extension, a `name` without an extension is also
+/// tried with each extension listed in `PATHEXT`.
+///
+/// If `name` contains a path separator, such as `./build.sh`, it is
+/// checked directly rather than searched for.
+#[derive(Clone, Debug)]
+pub struct Which {
+    name: OsString,
+    dirs: Vec<PathBuf>,
+}
+
+impl Which {
+    /// Constructs a search for `name` in the directories of the `PATH`
+    /// environment variable.
+    ///
+    /// Empty entries in `PATH` refer to the current directory.
+    pub fn new<S: AsRef<OsStr>>(name: S) -> Self {
+        let dirs = std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).collect())
+            .unwrap_or_default();
+        Which {
+            name: name.as_ref().to_os_string(),
+            dirs,
+        }
+    }
+
+    /// Searches `dirs`, in order, instead of the directories of `PATH`.
+    pub fn dirs<I, P>(mut self, dirs: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        self.dirs = dirs.into_iter().map(|d| d.as_ref().to_path_buf()).collect();
+        self
+    }
+}
+
+impl IntoIterator for Which {
+    type Item = PathBuf;
+    type IntoIter = WhichIter;
+
+    /// Returns every matching executable, in search order.
+    fn into_iter(self) -> WhichIter {
+        let name = Path::new(&self.name);
+        let dirs = if name.components().count() > 1 {
+            vec![PathBuf::new()]
+        } else {
+            self.dirs
+        };
+        WhichIter {
+            name: self.name,
+            dirs: dirs.into_iter(),
+            candidates: Vec::new().into_iter(),
+        }
+    }
+}
+
+/// An iterator over the executables found by a [`Which`] search.
+///
+/// [`Which`]: struct.Which.html
+pub struct WhichIter {
+    name: OsString,
+    dirs: vec::IntoIter<PathBuf>,
+    candidates: vec::IntoIter<PathBuf>,
+}
+
+impl Iterator for WhichIter {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        loop {
+            for candidate in &mut self.candidates {
+                if is_executable(&candidate) {
+                    return Some(candidate);
+                }
+            }
+            let mut dir = self.dirs.next()?;
+            if dir.as_os_str().is_empty() && Path::new(&self.name).is_relative() {
+                dir = PathBuf::from(".");
+            }
+            self.candidates = candidates(&dir.join(&self.name)).into_iter();
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn candidates(path: &Path) -> Vec<PathBuf> {
+    vec![path.to_path_buf()]
+}
+
+#[cfg(windows)]
+fn candidates(path: &Path) -> Vec<PathBuf> {
+    let mut candidates = vec![path.to_path_buf()];
+    if path.extension().is_none() {
+        let pathext = std::env::var_os("PATHEXT").unwrap_or_else(|| ".COM;.EXE;.BAT;.CMD".into());
+        for ext in pathext
+            .to_string_lossy()
+            .split(';')
+            .filter(|e| !e.is_empty())
+        {
+            let mut candidate = path.as_os_str().to_os_string();
+            candidate.push(ext);
+            candidates.push(candidate.into());
+        }
+    }
+    candidates
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let is_file = path.metadata().map(|m| m.is_file()).unwrap_or(false);
+    let c_path = match CString::new(path.as_os_str().as_bytes()) {
+        Ok(c_path) => c_path,
+        Err(_) => return false,
+    };
+    // access(2) checks permissions against the real user and group, taking
+    // supplementary groups and superuser privileges into account.
+    is_file && unsafe { libc::access(c_path.as_ptr(), libc::X_OK) } == 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.metadata().map(|m| m.is_file()).unwrap_or(false)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use tempdir::TempDir;
+
+    fn create(path: &Path, mode: u32) {
+        fs::write(path, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    #[test]
+    fn searches_dirs_in_order() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let dirs: Vec<_> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|d| tmp_dir.path().join(d))
+            .collect();
+        for dir in &dirs {
+            fs::create_dir(dir).unwrap();
+        }
+        create(&dirs[0].join("tool"), 0o644);
+        create(&dirs[1].join("tool"), 0o755);
+        fs::create_dir(dirs[2].join("tool")).unwrap();
+        create(&dirs[3].join("tool"), 0o700);
+
+        let found: Vec<_> = Which::new("tool").dirs(&dirs).into_iter().collect();
+        assert_eq!(found, vec![dirs[1].join("tool"), dirs[3].join("tool")]);
+        assert_eq!(Which::new("missing").dirs(&dirs).into_iter().next(), None);
+    }
+
+    #[test]
+    fn name_with_separator_is_not_searched() {
+        let tmp_dir = TempDir::new("test_where_is").unwrap();
+        let tool = tmp_dir.path().join("tool");
+        create(&tool, 0o755);
+
+        let found: Vec<_> = Which::new(&tool)
+            .dirs(["/nonexistent"])
+            .into_iter()
+            .collect();
+        assert_eq!(found, vec![tool]);
+    }
+}